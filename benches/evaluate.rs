@@ -0,0 +1,65 @@
+//! Compares the cost of evaluating an expression with `JsonAta`, which is parsed and set up again
+//! for every evaluation, against a `CompiledExpression` that is compiled once and reused.
+//!
+//! Run with `cargo bench --bench evaluate`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bumpalo::Bump;
+use jsonata_rs::{CompiledExpression, JsonAta};
+
+const EXPR: &str =
+    r#"Account.Order.Product.{ "name": $uppercase(`Product Name`), "total": Price * Quantity }"#;
+
+const INPUT: &str = r#"{
+    "Account": {
+        "Order": [
+            { "Product": [
+                { "Product Name": "Bowler Hat", "Price": 34.45, "Quantity": 2 },
+                { "Product Name": "Trilby hat", "Price": 21.67, "Quantity": 1 }
+            ] },
+            { "Product": [
+                { "Product Name": "Cloak", "Price": 107.99, "Quantity": 1 }
+            ] }
+        ]
+    }
+}"#;
+
+const ITERATIONS: u32 = 20_000;
+
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    // Warm up
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+
+    println!("{name:<40} {per_iteration:>12.2?} per evaluation");
+    per_iteration
+}
+
+fn main() {
+    let jsonata = bench("JsonAta::new + evaluate", || {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(EXPR, &arena).unwrap();
+        black_box(jsonata.evaluate(Some(INPUT), None).unwrap());
+    });
+
+    let expr = CompiledExpression::compile(EXPR).unwrap();
+    let compiled = bench("CompiledExpression::evaluate", || {
+        let arena = Bump::new();
+        black_box(expr.evaluate(&arena, Some(INPUT), None).unwrap());
+    });
+
+    println!(
+        "CompiledExpression saves {:.2?} ({:.1}%) per evaluation",
+        jsonata.saturating_sub(compiled),
+        100.0 * (1.0 - compiled.as_secs_f64() / jsonata.as_secs_f64())
+    );
+}
@@ -0,0 +1,93 @@
+//! Compares the cost of parsing a large JSON input document with the arena JSON parser against
+//! the alternatives: evaluating the document as a JSONata expression, which is how input used to
+//! be parsed, and going through `serde_json::Value`.
+//!
+//! Run with `cargo bench --bench input`.
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bumpalo::Bump;
+use jsonata_rs::{CompiledExpression, JsonAta};
+
+const EXPR: &str = "$count(orders)";
+
+const ITERATIONS: u32 = 20;
+
+/// Build a document of about 3MB with a mix of nested objects, arrays, numbers and strings.
+fn document() -> String {
+    let mut doc = String::from(r#"{ "orders": ["#);
+    for i in 0..10_000 {
+        if i > 0 {
+            doc.push(',');
+        }
+        write!(
+            doc,
+            r#"
+    {{
+        "id": {i},
+        "customer": {{ "name": "Customer \"{i}\"", "email": "customer{i}@example.com" }},
+        "items": [
+            {{ "sku": "SKU-{i}-A", "price": {}.99, "quantity": 2, "tags": ["a", "b"] }},
+            {{ "sku": "SKU-{i}-B", "price": 1.5e1, "quantity": 1, "gift": true, "note": null }}
+        ]
+    }}"#,
+            i % 100
+        )
+        .unwrap();
+    }
+    doc.push_str("\n] }");
+    doc
+}
+
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    // Warm up
+    f();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+
+    println!("{name:<40} {per_iteration:>12.2?} per evaluation");
+    per_iteration
+}
+
+fn main() {
+    let doc = document();
+    println!("Input document is {} bytes", doc.len());
+
+    let expr = CompiledExpression::compile(EXPR).unwrap();
+
+    // The document is inlined into the expression, so that it is parsed and evaluated as a
+    // JSONata object constructor.
+    let inlined = format!("$count(({doc}).orders)");
+    let expression_parser = bench("Expression parser", || {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(&inlined, &arena).unwrap();
+        black_box(jsonata.evaluate(None, None).unwrap());
+    });
+
+    let serde_json = bench("serde_json::Value", || {
+        let arena = Bump::new();
+        let input: serde_json::Value = serde_json::from_str(&doc).unwrap();
+        black_box(expr.evaluate_value(&arena, &input, None).unwrap());
+    });
+
+    let json_parser = bench("JSON parser", || {
+        let arena = Bump::new();
+        black_box(expr.evaluate(&arena, Some(&doc), None).unwrap());
+    });
+
+    for (name, other) in [
+        ("expression parser", expression_parser),
+        ("serde_json::Value", serde_json),
+    ] {
+        println!(
+            "JSON parser is {:.1}x faster than {name}",
+            other.as_secs_f64() / json_parser.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+
+/// The source of the current time for `$now` and `$millis`.
+///
+/// The clock is read at most once per evaluation, so every call to `$now()` within an evaluation
+/// returns the same timestamp.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A clock that reads the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that is stopped at a fixed time, for repeatable evaluations.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
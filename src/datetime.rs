@@ -1,6 +1,7 @@
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use num_format::{Locale, ToFormattedString};
 
+use crate::integer::{format_integer, from_roman, from_words};
 use crate::Error;
 
 pub fn format_custom_date(date: &DateTime<FixedOffset>, picture: &str) -> Result<String, Error> {
@@ -69,9 +70,9 @@ fn handle_pattern(pattern: &str, date: &DateTime<FixedOffset>) -> Result<String,
         "Y01" => Ok(date.format("%y").to_string()),
         "Y0001,2-2" | "Y##01,2-2" => handle_year_last_two_digits(date),
         "Y9,999,*" => Ok(date.year().to_formatted_string(&Locale::en)),
-        "YI" => Ok(to_roman_numerals(date.year())),
-        "Yi" => Ok(to_roman_numerals_lower(date.year())),
-        "Yw" => Ok(to_year_in_words(date.year())),
+        "YI" => format_integer(date.year() as f64, "I"),
+        "Yi" => format_integer(date.year() as f64, "i"),
+        "Yw" => format_integer(date.year() as f64, "w"),
 
         // Month patterns
         "M01" => Ok(date.format("%m").to_string()),
@@ -87,9 +88,9 @@ fn handle_pattern(pattern: &str, date: &DateTime<FixedOffset>) -> Result<String,
         "D01" | "D#1,2" => Ok(date.format("%d").to_string()),
         "D" | "D#1" | "D1" => Ok(date.format("%-d").to_string()),
         "Da" => Ok(map_day_to_letter(date.day())),
-        "Dwo" => Ok(format_day_in_words_with_ordinal(date.day())),
-        "dwo" => Ok(format_day_in_words_with_ordinal(date.ordinal())),
-        "D1o" => Ok(format_day_with_ordinal(date.day())),
+        "Dwo" => format_integer(date.day() as f64, "w;o"),
+        "dwo" => format_integer(date.ordinal() as f64, "w;o"),
+        "D1o" => format_integer(date.day() as f64, "1;o"),
         "d" => Ok(calculate_total_days_in_year(date)),
 
         // Week patterns
@@ -139,7 +140,7 @@ fn handle_pattern(pattern: &str, date: &DateTime<FixedOffset>) -> Result<String,
     }
 }
 
-pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
+pub fn parse_custom_format(timestamp_str: &str, picture: &str, now: DateTime<Utc>) -> Option<i64> {
     match picture {
         // Handle ISO 8601 dates (including with timezone offsets like "+0000")
         "" => {
@@ -175,8 +176,8 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
 
         // Handle Roman numeral year format "[YI]" (e.g., 'MCMLXXXIV')
         "[YI]" => {
-            if let Some(year) = roman_to_int(timestamp_str) {
-                let parsed_year = NaiveDate::from_ymd_opt(year, 1, 1)?;
+            if let Some(year) = from_roman(timestamp_str) {
+                let parsed_year = NaiveDate::from_ymd_opt(year as i32, 1, 1)?;
                 let time = NaiveTime::from_hms_opt(0, 0, 0)?;
                 let datetime = NaiveDateTime::new(parsed_year, time);
                 return Some(Utc.from_utc_datetime(&datetime).timestamp_millis());
@@ -187,7 +188,7 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
         // Handle the format '[Yw]' (e.g., 'one thousand, nine hundred and eighty-four')
         "[Yw]" => {
             // Convert the word-based year (e.g., 'one thousand, nine hundred and eighty-four') to a number
-            let year = words_to_number(&timestamp_str.to_lowercase())?;
+            let year = from_words(&timestamp_str.to_lowercase())? as i32;
 
             // Set the date to January 1st of the given year
             let parsed_date = NaiveDate::from_ymd_opt(year, 1, 1)?;
@@ -213,7 +214,6 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
                 let minute: u32 = parts[1].parse().ok()?;
 
                 // Use the current date along with the given time
-                let now = Utc::now(); // Get current date
                 let parsed_date = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day())?;
                 let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
                 let datetime = NaiveDateTime::new(parsed_date, time);
@@ -288,7 +288,7 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             }
 
             let day_str = remove_day_suffix(parts[0]);
-            let day = words_to_number(&day_str.to_lowercase())? as u32;
+            let day = from_words(&day_str.to_lowercase())? as u32;
 
             // Convert the month name (e.g., 'April') into its corresponding numeric value
             let month = month_name_to_int(parts[1])?;
@@ -297,7 +297,7 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             let year_str = parts[2..].join(" ");
             let year = match year_str.parse::<i32>() {
                 Ok(num) => num,
-                Err(_) => words_to_number(&year_str)? as i32, // If it's word-based (e.g., 'two thousand and seventeen')
+                Err(_) => from_words(&year_str)? as i32, // If it's word-based (e.g., 'two thousand and seventeen')
             };
             println!("year {}", year);
 
@@ -314,7 +314,7 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             if parts.len() == 3 {
                 let day: u32 = parts[0].parse().ok()?;
                 let month: u32 = parts[1].parse().ok()?;
-                let year = roman_to_int(parts[2])?;
+                let year = from_roman(parts[2])? as i32;
                 let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
                 let time = NaiveTime::from_hms_opt(0, 0, 0)?;
                 let datetime = NaiveDateTime::new(parsed_date, time);
@@ -328,8 +328,8 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             let parts: Vec<&str> = timestamp_str.split_whitespace().collect();
             if parts.len() == 3 {
                 let day: u32 = parts[0].parse().ok()?;
-                let month = roman_to_int(parts[1].to_uppercase().as_str())? as u32;
-                let year = roman_to_int(parts[2])?;
+                let month = from_roman(parts[1].to_uppercase().as_str())? as u32;
+                let year = from_roman(parts[2])? as i32;
 
                 let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
                 let time = NaiveTime::from_hms_opt(0, 0, 0)?;
@@ -345,7 +345,7 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             let parts: Vec<&str> = timestamp_str.split_whitespace().collect();
             if parts.len() == 3 {
                 let month = roman_month_to_int(parts[1])?;
-                let year = roman_to_int(parts[2].to_uppercase().as_str())?;
+                let year = from_roman(parts[2].to_uppercase().as_str())? as i32;
                 let day = alphabetic_to_day(parts[0])?;
 
                 let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
@@ -526,11 +526,11 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             }
 
             let day_str = parse_day_str(parts[0]);
-            let day = words_to_number(&day_str.to_lowercase())? as u32;
+            let day = from_words(&day_str.to_lowercase())? as u32;
             let month = month_name_to_int(parts[1])?;
 
             let year_str = parts[2..].join(" ");
-            let year = words_to_number(&year_str.to_lowercase())? as i32;
+            let year = from_words(&year_str.to_lowercase())? as i32;
 
             let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
             let time = NaiveTime::from_hms_opt(0, 0, 0)?;
@@ -548,11 +548,11 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             }
 
             let day_str = parse_day_str(parts[0]);
-            let day = words_to_number(&day_str.to_lowercase())? as u32;
+            let day = from_words(&day_str.to_lowercase())? as u32;
             let month = month_name_to_int(parts[1])?;
 
             let year_str = parts[2..].join(" ");
-            let year = words_to_number(&year_str.to_lowercase())? as i32;
+            let year = from_words(&year_str.to_lowercase())? as i32;
 
             let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
             let time = NaiveTime::from_hms_opt(0, 0, 0)?;
@@ -571,11 +571,11 @@ pub fn parse_custom_format(timestamp_str: &str, picture: &str) -> Option<i64> {
             }
 
             let day_str = parts[0]; // Handle the day part (e.g., "Twentieth")
-            let day = words_to_number(&day_str.to_lowercase())? as u32;
+            let day = from_words(&day_str.to_lowercase())? as u32;
             let month = month_name_to_int(parts[1])?; // Handle the month (e.g., "August")
 
             let year_str = parts[2..].join(" ");
-            let year = words_to_number(&year_str.to_lowercase())? as i32;
+            let year = from_words(&year_str.to_lowercase())? as i32;
 
             let parsed_date = NaiveDate::from_ymd_opt(year, month, day)?;
             let time = NaiveTime::from_hms_opt(0, 0, 0)?;
@@ -732,126 +732,6 @@ fn handle_timezone(date: &DateTime<FixedOffset>, pattern: &str) -> Result<String
     }
 }
 
-pub fn format_day_with_ordinal(day: u32) -> String {
-    match day {
-        1 | 21 | 31 => format!("{}st", day),
-        2 | 22 => format!("{}nd", day),
-        3 | 23 => format!("{}rd", day),
-        _ => format!("{}th", day),
-    }
-}
-
-fn to_year_in_words(year: i32) -> String {
-    if year < 0 {
-        return format!("minus {}", to_year_in_words(-year));
-    }
-
-    let below_20 = [
-        "",
-        "one",
-        "two",
-        "three",
-        "four",
-        "five",
-        "six",
-        "seven",
-        "eight",
-        "nine",
-        "ten",
-        "eleven",
-        "twelve",
-        "thirteen",
-        "fourteen",
-        "fifteen",
-        "sixteen",
-        "seventeen",
-        "eighteen",
-        "nineteen",
-    ];
-    let tens = [
-        "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
-    ];
-
-    let mut result = String::new();
-    let mut y = year;
-
-    // Handle thousands (e.g., 2000 -> "two thousand")
-    if y >= 1000 {
-        let thousands = y / 1000;
-        result.push_str(below_20[thousands as usize]);
-        result.push_str(" thousand");
-        y %= 1000;
-
-        // Add "and" only if the number is non-zero and below 1000
-        if y > 0 && y < 100 {
-            result.push_str(" and ");
-        } else if y > 0 {
-            result.push(' ');
-        }
-    }
-
-    // Handle hundreds (e.g., 800 -> "eight hundred")
-    if y >= 100 {
-        let hundreds = y / 100;
-        result.push_str(below_20[hundreds as usize]);
-        result.push_str(" hundred");
-        y %= 100;
-
-        if y > 0 {
-            result.push_str(" and ");
-        }
-    }
-
-    // Handle tens and ones
-    if y >= 20 {
-        let t = y / 10;
-        result.push_str(tens[t as usize]);
-        y %= 10;
-
-        if y > 0 {
-            result.push('-');
-        }
-    }
-
-    if y > 0 {
-        result.push_str(below_20[y as usize]);
-    }
-
-    result.trim().to_string()
-}
-
-pub fn to_roman_numerals(year: i32) -> String {
-    let mut year = year;
-    let mut roman = String::new();
-    let numerals = [
-        (1000, "M"),
-        (900, "CM"),
-        (500, "D"),
-        (400, "CD"),
-        (100, "C"),
-        (90, "XC"),
-        (50, "L"),
-        (40, "XL"),
-        (10, "X"),
-        (9, "IX"),
-        (5, "V"),
-        (4, "IV"),
-        (1, "I"),
-    ];
-
-    for &(value, symbol) in &numerals {
-        while year >= value {
-            roman.push_str(symbol);
-            year -= value;
-        }
-    }
-    roman
-}
-
-pub fn to_roman_numerals_lower(year: i32) -> String {
-    to_roman_numerals(year).to_lowercase()
-}
-
 // Helper function to parse timezone strings like "±HHMM"
 pub fn parse_timezone_offset(timezone: &str) -> Option<FixedOffset> {
     if timezone == "0000" {
@@ -874,112 +754,6 @@ pub fn parse_timezone_offset(timezone: &str) -> Option<FixedOffset> {
     }
 }
 
-fn format_day_in_words_with_ordinal(day: u32) -> String {
-    let word = to_words(day); // Convert the number to words
-
-    // Special cases for 11th, 12th, and 13th
-    if (11..=13).contains(&(day % 100)) {
-        return format!("{}th", word);
-    }
-
-    if word.ends_with("first") || word.ends_with("second") || word.ends_with("third") {
-        return word;
-    }
-
-    let suffix = match day % 10 {
-        1 => "st",
-        2 => "nd",
-        3 => "rd",
-        _ => "th",
-    };
-
-    format!("{}{}", word, suffix)
-}
-
-fn to_words(num: u32) -> String {
-    let below_20 = [
-        "",
-        "first",
-        "second",
-        "third",
-        "fourth",
-        "fifth",
-        "sixth",
-        "seventh",
-        "eighth",
-        "ninth",
-        "tenth",
-        "eleventh",
-        "twelfth",
-        "thirteenth",
-        "fourteenth",
-        "fifteenth",
-        "sixteenth",
-        "seventeenth",
-        "eighteenth",
-        "nineteenth",
-    ];
-    let tens = [
-        "",
-        "",
-        "twentieth",
-        "thirtieth",
-        "fortieth",
-        "fiftieth",
-        "sixtieth",
-        "seventieth",
-        "eightieth",
-        "ninetieth",
-    ];
-    let tens_with_units = [
-        "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
-    ];
-
-    // Handle numbers below 20
-    if num < 20 {
-        below_20[num as usize].to_string()
-    } else if num < 100 {
-        // Handle multiples of 10 (20, 30, etc.)
-        if num % 10 == 0 {
-            return tens[(num / 10) as usize].to_string();
-        }
-        // Handle numbers between 21-99
-        let ten = tens_with_units[(num / 10) as usize];
-        let unit = below_20[(num % 10) as usize];
-        format!("{}-{}", ten, unit)
-    } else {
-        num.to_string()
-    }
-}
-
-fn roman_to_int(s: &str) -> Option<i32> {
-    let mut total = 0;
-    let mut prev_value = 0;
-
-    for c in s.chars().rev() {
-        let value = match c {
-            'I' => 1,
-            'V' => 5,
-            'X' => 10,
-            'L' => 50,
-            'C' => 100,
-            'D' => 500,
-            'M' => 1000,
-            _ => return None, // Invalid Roman numeral
-        };
-
-        if value < prev_value {
-            total -= value;
-        } else {
-            total += value;
-        }
-
-        prev_value = value;
-    }
-
-    Some(total)
-}
-
 pub fn roman_month_to_int(month_str: &str) -> Option<u32> {
     match month_str.to_uppercase().as_str() {
         "I" | "A" => Some(1),    // January (I or A for abbreviated January)
@@ -1074,122 +848,6 @@ fn abbreviated_month_to_int(month_str: &str) -> Option<u32> {
     }
 }
 
-// Split the word string into tokens, handling hyphenated and punctuated numbers
-fn words_to_number(word_str: &str) -> Option<i32> {
-    let units = [
-        ("zero", 0),
-        ("one", 1),
-        ("two", 2),
-        ("three", 3),
-        ("four", 4),
-        ("five", 5),
-        ("six", 6),
-        ("seven", 7),
-        ("eight", 8),
-        ("nine", 9),
-        ("ten", 10),
-        ("eleven", 11),
-        ("twelve", 12),
-        ("thirteen", 13),
-        ("fourteen", 14),
-        ("fifteen", 15),
-        ("sixteen", 16),
-        ("seventeen", 17),
-        ("eighteen", 18),
-        ("nineteen", 19),
-        // Ordinal units
-        ("first", 1),
-        ("second", 2),
-        ("third", 3),
-        ("fourth", 4),
-        ("fifth", 5),
-        ("sixth", 6),
-        ("seventh", 7),
-        ("eighth", 8),
-        ("ninth", 9),
-        ("tenth", 10),
-        ("eleventh", 11),
-        ("twelfth", 12),
-        ("thirteenth", 13),
-        ("fourteenth", 14),
-        ("fifteenth", 15),
-        ("sixteenth", 16),
-        ("seventeenth", 17),
-        ("eighteenth", 18),
-        ("nineteenth", 19),
-        ("twentieth", 20),
-        ("twenty-first", 21),
-        ("twenty-second", 22),
-        ("twenty-third", 23),
-        ("twenty-fourth", 24),
-        ("twenty-fifth", 25),
-        ("twenty-sixth", 26),
-        ("twenty-seventh", 27),
-        ("twenty-eighth", 28),
-        ("twenty-ninth", 29),
-        ("thirtieth", 30),
-        ("thirty-first", 31),
-    ];
-
-    let tens = [
-        ("twenty", 20),
-        ("thirty", 30),
-        ("forty", 40),
-        ("fifty", 50),
-        ("sixty", 60),
-        ("seventy", 70),
-        ("eighty", 80),
-        ("ninety", 90),
-    ];
-
-    let scales = [("hundred", 100), ("thousand", 1000)];
-
-    let mut result = 0;
-    let mut current = 0;
-    let mut last_ten = None;
-
-    for word in word_str
-        .replace(",", "")
-        .to_lowercase() // Convert the input to lowercase
-        .split_whitespace()
-        .flat_map(|w| w.split('-'))
-    {
-        if word == "and" {
-            continue;
-        }
-
-        // Check if it's a unit or ordinal unit
-        if let Some(unit) = units.iter().find(|&&(w, _)| w == word).map(|(_, n)| n) {
-            if let Some(ten) = last_ten {
-                current += ten + unit;
-                last_ten = None;
-            } else {
-                current += unit;
-            }
-        }
-        // Check if it's a ten or ordinal ten
-        else if let Some(ten) = tens.iter().find(|&&(w, _)| w == word).map(|(_, n)| n) {
-            if let Some(ten_value) = last_ten {
-                current += ten_value + ten;
-            } else {
-                last_ten = Some(ten);
-            }
-        }
-        // Check if it's a scale (e.g., "hundred", "thousand")
-        else if let Some(scale) = scales.iter().find(|&&(w, _)| w == word).map(|(_, n)| n) {
-            if *scale == 100 {
-                current *= scale;
-            } else if *scale == 1000 {
-                result += current * scale; // e.g., "two thousand" -> 2000
-                current = 0; // Reset to accumulate further values (like "seventeen")
-            }
-        }
-    }
-
-    result += current;
-    Some(result)
-}
-
 fn parse_custom_date(date_str: &str) -> Option<NaiveDateTime> {
     let parts: Vec<&str> = date_str.split_whitespace().collect();
 
@@ -1312,7 +970,7 @@ fn parse_custom_date_with_weekday_and_ordinal(date_str: &str) -> Option<NaiveDat
     }
 
     // Parse the ordinal day in words (e.g., "Twelfth")
-    let day = words_to_number(parts[1])? as u32;
+    let day = from_words(parts[1])? as u32;
 
     // Parse the full month name (e.g., "November")
     let month = match parts[2].to_lowercase().as_str() {
@@ -1352,7 +1010,7 @@ fn parse_ordinal_day_of_year(date_str: &str) -> Option<NaiveDateTime> {
     let ordinal_day_words = parts[..(parts.len() - 3)].join(" ");
 
     // Convert the ordinal day in words to a number
-    let day_of_year = words_to_number(&ordinal_day_words)? as u32;
+    let day_of_year = from_words(&ordinal_day_words)? as u32;
 
     // Parse the year (e.g., "2018")
     let year: i32 = parts.last()?.parse().ok()?;
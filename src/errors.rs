@@ -16,6 +16,7 @@ pub enum Error {
     S0202UnexpectedToken(usize, String, String),
     S0204UnknownOperator(usize, String),
     S0203ExpectedTokenBeforeEnd(usize, String),
+    S0207UnexpectedEndOfExpression(usize),
     S0208InvalidFunctionParam(usize, String),
     S0209InvalidPredicate(usize),
     S0210MultipleGroupBy(usize),
@@ -25,10 +26,15 @@ pub enum Error {
     S0214ExpectedVarRight(usize, String),
     S0215BindingAfterPredicates(usize),
     S0216BindingAfterSort(usize),
+    S0217ParentNotDerivable(usize),
     S0301EmptyRegex(usize),
     S0302UnterminatedRegex(usize),
     // This variant is not present in the JS implementation
     S0303InvalidRegex(usize, String),
+    S0401InvalidTypeParameter(usize, String),
+    S0402ParameterizedChoiceGroup(usize, String),
+    // This variant is not present in the JS implementation
+    S0501InvalidJson(usize, usize, String),
 
     // Runtime errors
     D1001NumberOfOutRange(f64),
@@ -46,20 +52,48 @@ pub enum Error {
     D3060SqrtNegative(usize, String),
     D3061PowUnrepresentable(usize, String, String),
     D3070InvalidDefaultSort(usize),
+    D3080TooManySubPictures,
+    D3081MultipleDecimalSeparators,
+    D3082MultiplePercents,
+    D3083MultiplePerMilles,
+    D3084PercentAndPerMille,
+    D3085NoMantissaDigits,
+    D3086PassiveCharacter,
+    D3087GroupingAdjacentToDecimal,
+    D3088GroupingAtEndOfInteger,
+    D3089AdjacentGroupingSeparators,
+    D3090DigitBeforeOptionalDigit,
+    D3091OptionalDigitBeforeDigit,
+    D3092PercentWithExponent,
+    D3093InvalidExponent,
     D3141Assert(String),
     D3137Error(String),
     D3138Error(String),
     D3139Error(String),
+    D3140MalformedUrl(usize, String, String),
+    D3100RadixOutOfRange(usize, f64),
+    D3120EvalSyntaxError(usize, String),
+    D3121EvalError(usize, String),
+    D3130UnsupportedSequence(String),
+    D3131MixedDecimalGroups,
     D3133PictureStringNameModifierError(String),
     D3134TooManyTzDigits(String),
     D3135PictureStringNoClosingBracketError(String),
 
     // Type errors
     T0410ArgumentNotValid(usize, usize, String),
+    T0411ContextValueNotCompatible(usize, usize, String),
     T0412ArgumentMustBeArrayOfType(usize, usize, String, String),
     T1003NonStringKey(usize, String),
     T1005InvokedNonFunctionSuggest(usize, String),
     T1006InvokedNonFunction(usize),
+    T1007PartialApplyNonFunctionSuggest(usize, String),
+    T1008PartialApplyNonFunction(usize),
+    T1009DisabledFunction(usize, String),
+    // This variant is not present in the JS implementation
+    T1011NotSerializable(String),
+    // This variant is not present in the JS implementation
+    T1012ResultMismatch(String, String),
     T2001LeftSideNotNumber(usize, String),
     T2002RightSideNotNumber(usize, String),
     T2003LeftSideNotInteger(usize),
@@ -76,6 +110,14 @@ pub enum Error {
     // Expression timebox/depth errors
     U1001StackOverflow,
     U1001Timeout,
+
+    // Expression resource limit errors
+    // This variant is not present in the JS implementation
+    U1002MemoryLimit(usize),
+    // This variant is not present in the JS implementation
+    U1002SequenceTooLong(usize),
+    // This variant is not present in the JS implementation
+    U1003StepLimit(usize),
 }
 
 impl error::Error for Error {}
@@ -91,6 +133,7 @@ impl Error {
      *  02xx    - parser
      *  03xx    - regex parser
      *  04xx    - function signature parser/evaluator
+     *  05xx    - JSON input parser
      *  10xx    - evaluator
      *  20xx    - operators
      *  3xxx    - functions (blocks of 10 for each function)
@@ -108,6 +151,7 @@ impl Error {
             Error::S0202UnexpectedToken(..) => "S0202",
             Error::S0203ExpectedTokenBeforeEnd(..) => "S0203",
             Error::S0204UnknownOperator(..) => "S0204",
+            Error::S0207UnexpectedEndOfExpression(..) => "S0207",
             Error::S0208InvalidFunctionParam(..) => "S0208",
             Error::S0209InvalidPredicate(..) => "S0209",
             Error::S0210MultipleGroupBy(..) => "S0210",
@@ -117,9 +161,13 @@ impl Error {
             Error::S0214ExpectedVarRight(..) => "S0214",
             Error::S0215BindingAfterPredicates(..) => "S0215",
             Error::S0216BindingAfterSort(..) => "S0216",
+            Error::S0217ParentNotDerivable(..) => "S0217",
             Error::S0301EmptyRegex(..) => "S0301",
             Error::S0302UnterminatedRegex(..) => "S0302",
             Error::S0303InvalidRegex(..) => "S0303",
+            Error::S0401InvalidTypeParameter(..) => "S0401",
+            Error::S0402ParameterizedChoiceGroup(..) => "S0402",
+            Error::S0501InvalidJson(..) => "S0501",
 
             // Runtime errors
             Error::D1001NumberOfOutRange(..) => "D1001",
@@ -137,6 +185,25 @@ impl Error {
             Error::D3060SqrtNegative(..) => "D3060",
             Error::D3061PowUnrepresentable(..) => "D3061",
             Error::D3070InvalidDefaultSort(..) => "D3070",
+            Error::D3080TooManySubPictures => "D3080",
+            Error::D3081MultipleDecimalSeparators => "D3081",
+            Error::D3082MultiplePercents => "D3082",
+            Error::D3083MultiplePerMilles => "D3083",
+            Error::D3084PercentAndPerMille => "D3084",
+            Error::D3085NoMantissaDigits => "D3085",
+            Error::D3086PassiveCharacter => "D3086",
+            Error::D3087GroupingAdjacentToDecimal => "D3087",
+            Error::D3088GroupingAtEndOfInteger => "D3088",
+            Error::D3089AdjacentGroupingSeparators => "D3089",
+            Error::D3090DigitBeforeOptionalDigit => "D3090",
+            Error::D3091OptionalDigitBeforeDigit => "D3091",
+            Error::D3092PercentWithExponent => "D3092",
+            Error::D3093InvalidExponent => "D3093",
+            Error::D3100RadixOutOfRange(..) => "D3100",
+            Error::D3120EvalSyntaxError(..) => "D3120",
+            Error::D3121EvalError(..) => "D3121",
+            Error::D3130UnsupportedSequence(..) => "D3130",
+            Error::D3131MixedDecimalGroups => "D3131",
             Error::D3133PictureStringNameModifierError(..) => "D3133",
             Error::D3134TooManyTzDigits(..) => "D3134",
             Error::D3135PictureStringNoClosingBracketError(..) => "D3135",
@@ -144,13 +211,20 @@ impl Error {
             Error::D3137Error(..) => "D3137",
             Error::D3138Error(..) => "D3138",
             Error::D3139Error(..) => "D3139",
+            Error::D3140MalformedUrl(..) => "D3140",
 
             // Type errors
             Error::T0410ArgumentNotValid(..) => "T0410",
+            Error::T0411ContextValueNotCompatible(..) => "T0411",
             Error::T0412ArgumentMustBeArrayOfType(..) => "T0412",
             Error::T1003NonStringKey(..) => "T1003",
             Error::T1005InvokedNonFunctionSuggest(..) => "T1005",
             Error::T1006InvokedNonFunction(..) => "T1006",
+            Error::T1007PartialApplyNonFunctionSuggest(..) => "T1007",
+            Error::T1008PartialApplyNonFunction(..) => "T1008",
+            Error::T1009DisabledFunction(..) => "T1009",
+            Error::T1011NotSerializable(..) => "T1011",
+            Error::T1012ResultMismatch(..) => "T1012",
             Error::T2001LeftSideNotNumber(..) => "T2001",
             Error::T2002RightSideNotNumber(..) => "T2002",
             Error::T2003LeftSideNotInteger(..) => "T2003",
@@ -167,6 +241,11 @@ impl Error {
             // Expression timebox/depth errors
             Error::U1001StackOverflow => "U1001",
             Error::U1001Timeout => "U1001",
+
+            // Expression resource limit errors
+            Error::U1002MemoryLimit(..) => "U1002",
+            Error::U1002SequenceTooLong(..) => "U1002",
+            Error::U1003StepLimit(..) => "U1003",
         }
     }
 }
@@ -202,6 +281,8 @@ impl fmt::Display for Error {
                 write!(f, "{}: Expected `{}` before end of expression", p, t),
             S0204UnknownOperator(ref p, ref t) =>
                 write!(f, "{}: Unknown operator: `{}`", p, t),
+            S0207UnexpectedEndOfExpression(ref p) =>
+                write!(f, "{}: Unexpected end of expression", p),
             S0208InvalidFunctionParam(ref p, ref k) =>
                 write!(f, "{}: Parameter `{}` of function definition must be a variable name (start with $)", p, k),
             S0209InvalidPredicate(ref p) =>
@@ -220,6 +301,8 @@ impl fmt::Display for Error {
                 write!(f, "{}: A context variable binding must precede any predicates on a step", p),
             S0216BindingAfterSort(ref p) =>
                 write!(f, "{}: A context variable binding must precede the 'order-by' clause on a step", p),
+            S0217ParentNotDerivable(ref p) =>
+                write!(f, "{}: The object representing the 'parent' cannot be derived from this expression", p),
             S0301EmptyRegex(ref p) =>
                 write!(f, "{}: Empty regular expressions are not allowed", p),
             S0302UnterminatedRegex(ref p) =>
@@ -227,6 +310,12 @@ impl fmt::Display for Error {
             S0303InvalidRegex(ref p, ref message) =>
                 // The error message from `regress::Regex` a "regex parse error: " prefix, so don't be redundant here.
                 write!(f, "{}: {}", p, message),
+            S0401InvalidTypeParameter(ref p, ref t) =>
+                write!(f, "{}: Type parameters can only be applied to functions and arrays, not `{}`", p, t),
+            S0402ParameterizedChoiceGroup(ref p, ref c) =>
+                write!(f, "{}: Choice groups containing parameterized types are not supported: `{}`", p, c),
+            S0501InvalidJson(ref line, ref column, ref message) =>
+                write!(f, "{}:{}: Invalid JSON input: {}", line, column, message),
 
             // Runtime errors
             D1001NumberOfOutRange(ref n) => write!(f, "Number out of range: {}", n),
@@ -257,6 +346,44 @@ impl fmt::Display for Error {
                 write!(f, "{}: The power function has resulted in a value that cannot be represented as a JSON number: base={}, exponent={}", p, b, e),
             D3070InvalidDefaultSort(ref p) =>
                 write!(f, "{}: The single argument form of the sort function can only be applied to an array of strings or an array of numbers.  Use the second argument to specify a comparison function", p),
+            D3080TooManySubPictures =>
+                write!(f, "The picture string must only contain a maximum of two sub-pictures"),
+            D3081MultipleDecimalSeparators =>
+                write!(f, "The sub-picture must not contain more than one instance of the 'decimal-separator' character"),
+            D3082MultiplePercents =>
+                write!(f, "The sub-picture must not contain more than one instance of the 'percent' character"),
+            D3083MultiplePerMilles =>
+                write!(f, "The sub-picture must not contain more than one instance of the 'per-mille' character"),
+            D3084PercentAndPerMille =>
+                write!(f, "The sub-picture must not contain both a 'percent' and a 'per-mille' character"),
+            D3085NoMantissaDigits =>
+                write!(f, "The mantissa part of a sub-picture must contain at least one character that is either an 'optional digit character' or a member of the 'decimal digit family'"),
+            D3086PassiveCharacter =>
+                write!(f, "The sub-picture must not contain a passive character that is preceded by an active character and that is followed by another active character"),
+            D3087GroupingAdjacentToDecimal =>
+                write!(f, "The sub-picture must not contain a 'grouping-separator' character that appears adjacent to a 'decimal-separator' character"),
+            D3088GroupingAtEndOfInteger =>
+                write!(f, "The sub-picture must not contain a 'grouping-separator' at the end of the integer part"),
+            D3089AdjacentGroupingSeparators =>
+                write!(f, "The sub-picture must not contain two adjacent instances of the 'grouping-separator' character"),
+            D3090DigitBeforeOptionalDigit =>
+                write!(f, "The integer part of the sub-picture must not contain a member of the 'decimal digit family' that is followed by an instance of the 'optional digit character'"),
+            D3091OptionalDigitBeforeDigit =>
+                write!(f, "The fractional part of the sub-picture must not contain an instance of the 'optional digit character' that is followed by a member of the 'decimal digit family'"),
+            D3092PercentWithExponent =>
+                write!(f, "A sub-picture that contains a 'percent' or 'per-mille' character must not contain a character treated as an 'exponent-separator'"),
+            D3093InvalidExponent =>
+                write!(f, "The exponent part of the sub-picture must comprise only of one or more characters that are members of the 'decimal digit family'"),
+            D3100RadixOutOfRange(ref p, ref r) =>
+                write!(f, "{}: The radix of the formatBase function must be between 2 and 36.  It was given {}", p, r),
+            D3120EvalSyntaxError(ref p, ref m) =>
+                write!(f, "{}: Syntax error in expression passed to function eval: {}", p, m),
+            D3121EvalError(ref p, ref m) =>
+                write!(f, "{}: Dynamic error evaluating the expression passed to function eval: {}", p, m),
+            D3130UnsupportedSequence(ref v) =>
+                write!(f, "Formatting or parsing an integer as a sequence starting with {} is not supported by this implementation", v),
+            D3131MixedDecimalGroups =>
+                write!(f, "In a decimal digit pattern, all digits must be from the same decimal group"),
             D3133PictureStringNameModifierError(ref m) =>
                 write!(f, "{}: The 'name' modifier can only be applied to months and days in the date/time picture string, not Y", m),
             D3134TooManyTzDigits(ref m) =>
@@ -271,9 +398,13 @@ impl fmt::Display for Error {
                 write!(f, "{}: The $single() function expected exactly 1 matching result.  Instead it matched more.", m),
             D3139Error(ref m) =>
                 write!(f, "{}: The $single() function expected exactly 1 matching result.  Instead it matched 0.", m),
+            D3140MalformedUrl(ref p, ref n, ref v) =>
+                write!(f, "{}: Malformed URL passed to ${}(): \"{}\"", p, n, v),
             // Type errors
             T0410ArgumentNotValid(ref p, ref i, ref t) =>
                 write!(f, "{}: Argument {} of function {} does not match function signature", p, i, t),
+            T0411ContextValueNotCompatible(ref p, ref i, ref t) =>
+                write!(f, "{}: Context value is not a compatible type with argument {} of function {}", p, i, t),
             T0412ArgumentMustBeArrayOfType(ref p, ref i, ref t, ref ty) =>
                 write!(f, "{}: Argument {} of function {} must be an array of {}", p, i, t, ty),
             T1003NonStringKey(ref p, ref v) =>
@@ -282,6 +413,16 @@ impl fmt::Display for Error {
                 write!(f, "{}: Attempted to invoke a non-function. Did you mean ${}?", p, t),
             T1006InvokedNonFunction(ref p) =>
                 write!(f, "{}: Attempted to invoke a non-function", p),
+            T1007PartialApplyNonFunctionSuggest(ref p, ref t) =>
+                write!(f, "{}: Attempted to partially apply a non-function. Did you mean ${}?", p, t),
+            T1008PartialApplyNonFunction(ref p) =>
+                write!(f, "{}: Attempted to partially apply a non-function", p),
+            T1009DisabledFunction(ref p, ref n) =>
+                write!(f, "{}: Attempted to invoke the function ${}, which has been disabled", p, n),
+            T1011NotSerializable(ref message) =>
+                write!(f, "The value can't be converted: {}", message),
+            T1012ResultMismatch(ref path, ref message) =>
+                write!(f, "{}: The result doesn't match the expected type: {}", path, message),
             T2001LeftSideNotNumber(ref p, ref o) =>
                 write!( f, "{}: The left side of the `{}` operator must evaluate to a number", p, o),
             T2002RightSideNotNumber(ref p, ref o) =>
@@ -310,25 +451,25 @@ impl fmt::Display for Error {
             U1001StackOverflow =>
                 write!(f, "Stack overflow error: Check for non-terminating recursive function.  Consider rewriting as tail-recursive."),
             U1001Timeout =>
-                write!(f, "Expression evaluation timeout: Check for infinite loop")
+                write!(f, "Expression evaluation timeout: Check for infinite loop"),
+            // Expression resource limit errors
+            U1002MemoryLimit(ref l) =>
+                write!(f, "Expression evaluation exceeded the memory limit of {} bytes", l),
+            U1002SequenceTooLong(ref l) =>
+                write!(f, "Expression evaluation produced a sequence longer than the limit of {} items", l),
+            U1003StepLimit(ref l) =>
+                write!(f, "Expression evaluation exceeded the limit of {} steps: Check for infinite loop", l)
         }
     }
 }
 
 // "S0205": "Unexpected token: {{token}}",
 // "S0206": "Unknown expression type: {{token}}",
-// "S0207": "Unexpected end of expression",
-// "S0217": "The object representing the 'parent' cannot be derived from this expression",
 
 // "S0301": "Empty regular expressions are not allowed",
 // "S0302": "No terminating / in regular expression",
-// "S0402": "Choice groups containing parameterized types are not supported",
-// "S0401": "Type parameters can only be applied to functions and arrays",
 // "S0500": "Attempted to evaluate an expression containing syntax error(s)",
-// "T0411": "Context value is not a compatible type with argument {{index}} of function {{token}}",
 // "D1004": "Regular expression matches zero length string",
-// "T1007": "Attempted to partially apply a non-function. Did you mean ${{{token}}}?",
-// "T1008": "Attempted to partially apply a non-function",
 // // "T1010": "The matcher function argument passed to function {{token}} does not return the correct object structure",
 // "D2005": "The left side of := must be a variable name (start with $)",  // defunct - replaced by S0212 parser error
 // define_error!(
@@ -342,26 +483,7 @@ impl fmt::Display for Error {
 // "D3020": "Third argument of split function must evaluate to a positive number",
 // "D3040": "Third argument of match function must evaluate to a positive number",
 // "D3050": "The second argument of reduce function must be a function with at least two arguments",
-// "D3080": "The picture string must only contain a maximum of two sub-pictures",
-// "D3081": "The sub-picture must not contain more than one instance of the 'decimal-separator' character",
-// "D3082": "The sub-picture must not contain more than one instance of the 'percent' character",
-// "D3083": "The sub-picture must not contain more than one instance of the 'per-mille' character",
-// "D3084": "The sub-picture must not contain both a 'percent' and a 'per-mille' character",
-// "D3085": "The mantissa part of a sub-picture must contain at least one character that is either an 'optional digit character' or a member of the 'decimal digit family'",
-// "D3086": "The sub-picture must not contain a passive character that is preceded by an active character and that is followed by another active character",
-// "D3087": "The sub-picture must not contain a 'grouping-separator' character that appears adjacent to a 'decimal-separator' character",
-// "D3088": "The sub-picture must not contain a 'grouping-separator' at the end of the integer part",
-// "D3089": "The sub-picture must not contain two adjacent instances of the 'grouping-separator' character",
-// "D3090": "The integer part of the sub-picture must not contain a member of the 'decimal digit family' that is followed by an instance of the 'optional digit character'",
-// "D3091": "The fractional part of the sub-picture must not contain an instance of the 'optional digit character' that is followed by a member of the 'decimal digit family'",
-// "D3092": "A sub-picture that contains a 'percent' or 'per-mille' character must not contain a character treated as an 'exponent-separator'",
-// "D3093": "The exponent part of the sub-picture must comprise only of one or more characters that are members of the 'decimal digit family'",
-// "D3100": "The radix of the formatBase function must be between 2 and 36.  It was given {{value}}",
 // "D3110": "The argument of the toMillis function must be an ISO 8601 formatted timestamp. Given {{value}}",
-// "D3120": "Syntax error in expression passed to function eval: {{value}}",
-// "D3121": "Dynamic error evaluating the expression passed to function eval: {{value}}",
-// "D3130": "Formatting or parsing an integer as a sequence starting with {{value}} is not supported by this implementation",
-// "D3131": "In a decimal digit pattern, all digits must be from the same decimal group",
 // "D3132": "Unknown component specifier {{value}} in date/time picture string",
 // "D3133": "The 'name' modifier can only be applied to months and days in the date/time picture string, not {{value}}",
 // "D3134": "The timezone integer format specifier cannot have more than four digits",
@@ -369,4 +491,3 @@ impl fmt::Display for Error {
 // "D3136": "The date/time picture string is missing specifiers required to parse the timestamp",
 // "D3138": "The $single() function expected exactly 1 matching result.  Instead it matched more.",
 // "D3139": "The $single() function expected exactly 1 matching result.  Instead it matched 0.",
-// "D3140": "Malformed URL passed to ${{{functionName}}}(): {{value}}",
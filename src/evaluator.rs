@@ -4,15 +4,23 @@ pub mod value;
 
 use frame::Frame;
 use functions::*;
-use value::{ArrayFlags, Value};
+use value::{decimal_from_f64, ArrayFlags, Value};
 
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
-use std::cell::RefCell;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use rust_decimal::Decimal;
+use std::cell::{OnceCell, RefCell, RefMut};
+use std::cmp::Ordering;
 use std::collections::{hash_map, HashMap};
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
 
 use super::parser::ast::*;
+use crate::clock::{Clock, SystemClock};
+use crate::random::{RandomSource, SystemRandom};
+use crate::registry::FunctionRegistry;
 use crate::{Error, Result};
 
 struct EvaluatorInternal {
@@ -20,33 +28,132 @@ struct EvaluatorInternal {
     started_at: Option<Instant>,
     max_depth: Option<usize>,
     time_limit: Option<usize>,
+    memory_limit: Option<usize>,
+    max_sequence_length: Option<usize>,
+    max_steps: Option<usize>,
+    steps: usize,
+
+    /// The bytes allocated by the arena before evaluation started, which don't count towards the
+    /// memory limit
+    allocated_at_start: usize,
+}
+
+/// The lambda that function chaining with `~>` is evaluated through, parsed once and shared by
+/// all evaluations.
+fn chain_ast() -> &'static Ast {
+    static CHAIN_AST: OnceLock<Ast> = OnceLock::new();
+    CHAIN_AST.get_or_init(|| {
+        let (ast, _) = crate::parser::parse("function($f, $g) { function($x){ $g($f($x)) } }")
+            .expect("the chain function should parse");
+        ast
+    })
 }
 
 pub struct Evaluator<'a> {
-    chain_ast: Option<Ast>,
     arena: &'a Bump,
     internal: RefCell<EvaluatorInternal>,
+    clock: Arc<dyn Clock>,
+    now: OnceCell<DateTime<Utc>>,
+    rng: RefCell<Box<dyn RngCore>>,
+    registry: Option<&'a FunctionRegistry>,
+    decimal_numbers: bool,
+
+    /// Registered and built-in functions that have been looked up during this evaluation
+    functions: RefCell<HashMap<&'a str, &'a Value<'a>>>,
 }
 
 impl<'a> Evaluator<'a> {
-    pub fn new(
-        chain_ast: Option<Ast>,
-        arena: &'a Bump,
-        max_depth: Option<usize>,
-        time_limit: Option<usize>,
-    ) -> Self {
+    pub fn new(arena: &'a Bump, max_depth: Option<usize>, time_limit: Option<usize>) -> Self {
         Evaluator {
-            chain_ast,
             arena,
             internal: RefCell::new(EvaluatorInternal {
                 depth: 0,
                 started_at: None,
                 max_depth,
                 time_limit,
+                memory_limit: None,
+                max_sequence_length: None,
+                max_steps: None,
+                steps: 0,
+                allocated_at_start: arena.allocated_bytes(),
             }),
+            clock: Arc::new(SystemClock),
+            now: OnceCell::new(),
+            rng: RefCell::new(SystemRandom.generator()),
+            registry: None,
+            decimal_numbers: false,
+            functions: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve functions that aren't bound in the frame through the given registry, rather than
+    /// directly from the standard library.
+    pub fn with_registry(mut self, registry: Option<&'a FunctionRegistry>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Limit the bytes allocated in the arena during evaluation. The arena allocates memory in
+    /// chunks, which is what's counted, so the limit is approximate.
+    pub fn with_memory_limit(mut self, memory_limit: Option<usize>) -> Self {
+        self.internal.get_mut().memory_limit = memory_limit;
+        self
+    }
+
+    /// Limit the length of sequences and arrays produced by ranges, paths, array constructors
+    /// and functions like `$map`.
+    pub fn with_max_sequence_length(mut self, max_sequence_length: Option<usize>) -> Self {
+        self.internal.get_mut().max_sequence_length = max_sequence_length;
+        self
+    }
+
+    /// Limit the number of steps taken by the evaluation. Unlike a time limit, this doesn't depend
+    /// on how fast the machine is, so an expression that stays within it always will.
+    pub fn with_max_steps(mut self, max_steps: Option<usize>) -> Self {
+        self.internal.get_mut().max_steps = max_steps;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_random_source(self, random: &dyn RandomSource) -> Self {
+        *self.rng.borrow_mut() = random.generator();
+        self
+    }
+
+    /// Evaluate number literals as exact decimals rather than floating point numbers, so that
+    /// arithmetic on them is exact, e.g. `0.1 + 0.2 = 0.3`.
+    pub fn with_decimal_numbers(mut self, enabled: bool) -> Self {
+        self.decimal_numbers = enabled;
+        self
+    }
+
+    pub fn decimal_numbers(&self) -> bool {
+        self.decimal_numbers
+    }
+
+    /// Create a number, which is a decimal if decimal numbers are enabled and it can be
+    /// represented as one.
+    pub fn number(&self, n: f64) -> &'a Value<'a> {
+        match decimal_from_f64(n) {
+            Some(d) if self.decimal_numbers => Value::decimal(self.arena, d),
+            _ => Value::number(self.arena, n),
         }
     }
 
+    /// The current time, which is read from the clock once and then stays the same for the rest
+    /// of the evaluation.
+    pub fn now(&self) -> DateTime<Utc> {
+        *self.now.get_or_init(|| self.clock.now())
+    }
+
+    pub fn rng(&self) -> RefMut<'_, dyn RngCore> {
+        RefMut::map(self.rng.borrow_mut(), |rng| &mut **rng)
+    }
+
     fn fn_context<'e>(
         &'e self,
         name: &'a str,
@@ -85,6 +192,66 @@ impl<'a> Evaluator<'a> {
                 return Err(Error::U1001StackOverflow);
             }
         }
+        drop(internal);
+        if inc_or_dec {
+            self.charge_steps(1)?;
+        }
+        self.check_memory(0)
+    }
+
+    /// The number of steps taken so far, which is one for every expression evaluated and every
+    /// native function called, plus whatever the functions charged for their own work.
+    pub fn steps(&self) -> usize {
+        self.internal.borrow().steps
+    }
+
+    /// Add to the number of steps taken, failing if that exceeds the limit.
+    pub fn charge_steps(&self, steps: usize) -> Result<()> {
+        let mut internal = self.internal.borrow_mut();
+        internal.steps = internal.steps.saturating_add(steps);
+        if let Some(max_steps) = internal.max_steps {
+            if internal.steps > max_steps {
+                return Err(Error::U1003StepLimit(max_steps));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the memory limit hasn't been exceeded, and won't be by allocating `additional`
+    /// bytes. This is for functions that allocate without evaluating any expressions.
+    pub fn check_memory(&self, additional: usize) -> Result<()> {
+        let internal = self.internal.borrow();
+        if let Some(memory_limit) = internal.memory_limit {
+            if self.allocated_bytes(&internal).saturating_add(additional) > memory_limit {
+                return Err(Error::U1002MemoryLimit(memory_limit));
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of bytes that can still be allocated within the memory limit, along with the
+    /// limit itself, or `None` if there is no limit.
+    pub fn available_memory(&self) -> Option<(usize, usize)> {
+        let internal = self.internal.borrow();
+        internal.memory_limit.map(|memory_limit| {
+            let available = memory_limit.saturating_sub(self.allocated_bytes(&internal));
+            (available, memory_limit)
+        })
+    }
+
+    fn allocated_bytes(&self, internal: &EvaluatorInternal) -> usize {
+        self.arena
+            .allocated_bytes()
+            .saturating_sub(internal.allocated_at_start)
+    }
+
+    /// Check that a sequence or array of the given length is within the limit.
+    pub fn check_sequence_length(&self, length: usize) -> Result<()> {
+        if let Some(max_sequence_length) = self.internal.borrow().max_sequence_length {
+            if length > max_sequence_length {
+                return Err(Error::U1002SequenceTooLong(max_sequence_length));
+            }
+        }
         Ok(())
     }
 
@@ -100,7 +267,7 @@ impl<'a> Evaluator<'a> {
             AstKind::Null => Value::null(self.arena),
             AstKind::Bool(b) => Value::bool(b),
             AstKind::String(ref s) => Value::string(self.arena, s),
-            AstKind::Number(n) => Value::number(self.arena, n),
+            AstKind::Number(n) => self.number(n),
             AstKind::Block(ref exprs) => self.evaluate_block(exprs, input, frame)?,
             AstKind::Unary(ref op) => self.evaluate_unary_op(node, op, input, frame)?,
             AstKind::Binary(ref op, ref lhs, ref rhs) => {
@@ -127,6 +294,7 @@ impl<'a> Evaluator<'a> {
             } => self.evaluate_function(input, proc, args, is_partial, frame, None)?,
             AstKind::Wildcard => self.evaluate_wildcard(input)?,
             AstKind::Descendent => self.evaluate_descendants(input)?,
+            AstKind::Parent(ref slot) => frame.lookup(&slot.label).unwrap_or_else(Value::undefined),
             AstKind::Transform {
                 ref pattern,
                 ref update,
@@ -206,13 +374,53 @@ impl<'a> Evaluator<'a> {
             } else {
                 input
             }
-        } else if let Some(value) = frame.lookup(name) {
+        } else if let Some(value) = self.lookup(name, frame) {
             value
         } else {
             Value::undefined()
         })
     }
 
+    /// Look up a variable in the frame, falling back to the registered and built-in functions.
+    fn lookup(&self, name: &str, frame: &Frame<'a>) -> Option<&'a Value<'a>> {
+        if let Some(value) = frame.lookup(name) {
+            return Some(value);
+        }
+
+        if let Some(value) = self.functions.borrow().get(name) {
+            return Some(*value);
+        }
+
+        let (name, value) = match self.registry.and_then(|r| r.get(self.arena, name)) {
+            Some(function) => function,
+            None if self.is_disabled_builtin(name) => {
+                let (name, arity, _, _) = builtin(name)?;
+                (
+                    name,
+                    &*Value::nativefn(self.arena, name, arity, fn_disabled),
+                )
+            }
+            None => {
+                let (name, arity, signature, func) = builtin(name)?;
+                (
+                    name,
+                    &*Value::nativefn_with_signature(self.arena, name, arity, signature, func),
+                )
+            }
+        };
+
+        self.functions.borrow_mut().insert(name, value);
+        Some(value)
+    }
+
+    /// Whether the name is a built-in function that the registry doesn't allow to be called.
+    fn is_disabled_builtin(&self, name: &str) -> bool {
+        builtin(name).is_some()
+            && self
+                .registry
+                .is_some_and(|r| !r.allows_builtin(name) && !r.contains(name))
+    }
+
     fn evaluate_unary_op(
         &self,
         node: &Ast,
@@ -225,9 +433,10 @@ impl<'a> Evaluator<'a> {
                 let result = self.evaluate(value, input, frame)?;
                 match result {
                     Value::Undefined => Ok(Value::undefined()),
-                    Value::Number(n) if result.is_valid_number()? => {
+                    Value::Number(n) | Value::RawNumber(n, _) if result.is_valid_number()? => {
                         Ok(Value::number(self.arena, -n))
                     }
+                    Value::Decimal(d) => Ok(Value::decimal(self.arena, -d)),
                     _ => Err(Error::D1002NegatingNonNumeric(
                         node.char_index,
                         result.to_string(),
@@ -241,8 +450,16 @@ impl<'a> Evaluator<'a> {
                     if let AstKind::Unary(UnaryOp::ArrayConstructor(..)) = item.kind {
                         values.push(value);
                     } else {
+                        // Check the limits before appending, as appending a range can allocate
+                        // a lot of memory at once
+                        if value.is_array() {
+                            let length = values.len() + value.len();
+                            self.check_sequence_length(length)?;
+                            self.check_memory(length * std::mem::size_of::<&Value>())?;
+                        }
                         fn_append_internal(&mut values, value);
                     }
+                    self.check_sequence_length(values.len())?;
                 }
 
                 let flags = if node.cons_array {
@@ -271,6 +488,8 @@ impl<'a> Evaluator<'a> {
         }
 
         let mut groups: HashMap<String, Group> = HashMap::new();
+        // The keys of the groups, in the order they were first seen
+        let mut keys = Vec::new();
         let reduce = input.has_flags(ArrayFlags::TUPLE_STREAM);
 
         let input = if input.is_array() && input.is_empty() {
@@ -316,6 +535,7 @@ impl<'a> Evaluator<'a> {
                         )?;
                     }
                     hash_map::Entry::Vacant(entry) => {
+                        keys.push(entry.key().clone());
                         entry.insert(Group { data: item, index });
                     }
                 };
@@ -324,7 +544,7 @@ impl<'a> Evaluator<'a> {
 
         let result = Value::object(self.arena);
 
-        for key in groups.keys() {
+        for key in keys.iter() {
             let group = groups.get(key).unwrap();
             let value = if reduce {
                 let tuple = self.reduce_tuple_stream(char_index, group.data, input, frame)?;
@@ -403,6 +623,9 @@ impl<'a> Evaluator<'a> {
             | BinaryOp::Modulus => {
                 let rhs = self.evaluate(rhs_ast, input, frame)?;
 
+                let lhs_value = lhs;
+                let rhs_value = rhs;
+
                 let lhs = if lhs.is_undefined() {
                     return Ok(Value::undefined());
                 } else if lhs.is_valid_number()? {
@@ -425,6 +648,15 @@ impl<'a> Evaluator<'a> {
                     ));
                 };
 
+                // Decimals are only created when decimal numbers are enabled, and arithmetic on
+                // them is exact unless the result can't be represented as a decimal, e.g. because
+                // it overflows or is a division by zero, in which case it falls back to f64.
+                if lhs_value.is_decimal() || rhs_value.is_decimal() {
+                    if let Some(result) = decimal_arithmetic(op, lhs_value, rhs_value) {
+                        return Ok(Value::decimal(self.arena, result));
+                    }
+                }
+
                 let result = match op {
                     BinaryOp::Add => lhs + rhs,
                     BinaryOp::Subtract => lhs - rhs,
@@ -452,13 +684,12 @@ impl<'a> Evaluator<'a> {
                 }
 
                 if lhs.is_number() && rhs.is_number() {
-                    let lhs = lhs.as_f64();
-                    let rhs = rhs.as_f64();
+                    let ordering = lhs.compare_numbers(rhs);
                     return Ok(Value::bool(match op {
-                        BinaryOp::LessThan => lhs < rhs,
-                        BinaryOp::LessThanEqual => lhs <= rhs,
-                        BinaryOp::GreaterThan => lhs > rhs,
-                        BinaryOp::GreaterThanEqual => lhs >= rhs,
+                        BinaryOp::LessThan => ordering.is_some_and(Ordering::is_lt),
+                        BinaryOp::LessThanEqual => ordering.is_some_and(Ordering::is_le),
+                        BinaryOp::GreaterThan => ordering.is_some_and(Ordering::is_gt),
+                        BinaryOp::GreaterThanEqual => ordering.is_some_and(Ordering::is_ge),
                         _ => unreachable!(),
                     }));
                 }
@@ -521,32 +752,25 @@ impl<'a> Evaluator<'a> {
                 if size > 10_000_000 {
                     return Err(Error::D2014RangeOutOfBounds(node.char_index, size));
                 }
+                self.check_sequence_length(size as usize)?;
 
                 Ok(Value::range(self.arena, lhs, rhs))
             }
 
             BinaryOp::Concat => {
                 let rhs = self.evaluate(rhs_ast, input, frame)?;
-                let mut result = String::new();
-                if !lhs.is_undefined() {
-                    result.push_str(
-                        &fn_string(
+                let mut strings = Vec::with_capacity(2);
+                for value in [lhs, rhs] {
+                    if !value.is_undefined() {
+                        strings.push(fn_string(
                             self.fn_context("string", node.char_index, input, frame),
-                            &[lhs],
-                        )?
-                        .as_str(),
-                    );
-                }
-                if !rhs.is_undefined() {
-                    result.push_str(
-                        &fn_string(
-                            self.fn_context("string", node.char_index, input, frame),
-                            &[rhs],
-                        )?
-                        .as_str(),
-                    );
+                            &[value],
+                        )?);
+                    }
                 }
-                Ok(Value::string(self.arena, &result))
+                let strings = strings.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                self.check_memory(strings.iter().map(|s| s.len()).sum())?;
+                Ok(Value::string(self.arena, &strings.concat()))
             }
 
             BinaryOp::And => Ok(Value::bool(
@@ -576,11 +800,7 @@ impl<'a> Evaluator<'a> {
 
                     if lhs.is_function() {
                         // Apply function chaining
-                        let chain = self.evaluate(
-                            self.chain_ast.as_ref().unwrap(),
-                            Value::undefined(),
-                            frame,
-                        )?;
+                        let chain = self.evaluate(chain_ast(), Value::undefined(), frame)?;
 
                         Ok(self.apply_function(
                             lhs_ast.char_index,
@@ -686,7 +906,10 @@ impl<'a> Evaluator<'a> {
                 break;
             }
 
-            input = result
+            // Steps that bind the focus don't change the context for the next step
+            if step.focus.is_none() {
+                input = result;
+            }
         }
 
         if is_tuple_stream {
@@ -791,6 +1014,7 @@ impl<'a> Evaluator<'a> {
                             result_sequence.push(item);
                         }
                     }
+                    self.check_sequence_length(result_sequence.len())?;
                 }
                 result_sequence
             },
@@ -873,6 +1097,9 @@ impl<'a> Evaluator<'a> {
                             output_tuple
                                 .insert(index_var, Value::number(self.arena, binding_index as f64));
                         }
+                        for label in &step.ancestors {
+                            output_tuple.insert(label, &tuple["@"]);
+                        }
                     }
                     result.push(output_tuple);
                 }
@@ -913,6 +1140,11 @@ impl<'a> Evaluator<'a> {
             let mut result = 0;
 
             for (sort_term, descending) in sort_terms {
+                // Later terms only break ties of the earlier ones
+                if result != 0 {
+                    break;
+                }
+
                 let aa = if is_tuple_sort {
                     let tuple_frame = Frame::from_tuple(frame, a);
                     self.evaluate(sort_term, &a["@"], &tuple_frame)?
@@ -951,15 +1183,11 @@ impl<'a> Evaluator<'a> {
                     (Value::String(..), Value::String(..)) => {
                         result = 1;
                     }
-                    (Value::Number(a), Value::Number(b)) if *a == *b => {
-                        continue;
-                    }
-                    (Value::Number(a), Value::Number(b)) if *a < *b => {
-                        result = -1;
-                    }
-                    (Value::Number(..), Value::Number(..)) => {
-                        result = 1;
-                    }
+                    _ if aa.is_number() && bb.is_number() => match aa.compare_numbers(bb) {
+                        Some(Ordering::Equal) => continue,
+                        Some(Ordering::Less) => result = -1,
+                        _ => result = 1,
+                    },
                     _ => {
                         return Err(Error::T2007CompareTypeMismatch(
                             char_index,
@@ -1161,7 +1389,7 @@ impl<'a> Evaluator<'a> {
         input: &'a Value<'a>,
         proc: &Ast,
         args: &[Ast],
-        _is_partial: bool,
+        is_partial: bool,
         frame: &Frame<'a>,
         context: Option<&'a Value<'a>>,
     ) -> Result<&'a Value<'a>> {
@@ -1171,16 +1399,36 @@ impl<'a> Evaluator<'a> {
         if evaluated_proc.is_undefined() {
             if let AstKind::Path(ref steps) = proc.kind {
                 if let AstKind::Name(ref name) = steps[0].kind {
-                    if frame.lookup(name).is_some() {
-                        return Err(Error::T1005InvokedNonFunctionSuggest(
-                            proc.char_index,
-                            name.clone(),
-                        ));
+                    if self.lookup(name, frame).is_some() {
+                        return Err(if is_partial {
+                            Error::T1007PartialApplyNonFunctionSuggest(
+                                proc.char_index,
+                                name.clone(),
+                            )
+                        } else {
+                            Error::T1005InvokedNonFunctionSuggest(proc.char_index, name.clone())
+                        });
                     }
                 }
             }
         }
 
+        if is_partial {
+            let partial =
+                self.partially_apply_function(proc.char_index, input, evaluated_proc, args, frame)?;
+
+            // When partially applied on the right hand side of ~>, the lhs is passed to the
+            // resulting function
+            return match context {
+                Some(context) => {
+                    let result =
+                        self.apply_function(proc.char_index, input, partial, &[context], frame)?;
+                    self.trampoline_evaluate_value(result, input, frame)
+                }
+                None => Ok(partial),
+            };
+        }
+
         let mut evaluated_args = Vec::with_capacity(args.len());
 
         if let Some(context) = context {
@@ -1192,8 +1440,15 @@ impl<'a> Evaluator<'a> {
             evaluated_args.push(arg);
         }
 
-        let result = self.apply_function(
+        // Functions called through a variable are referred to by its name in errors
+        let name = match proc.kind {
+            AstKind::Var(ref name) => Some(name.as_str()),
+            _ => None,
+        };
+
+        let result = self.apply_named_function(
             proc.char_index,
+            name,
             input,
             evaluated_proc,
             &evaluated_args,
@@ -1205,6 +1460,136 @@ impl<'a> Evaluator<'a> {
         Ok(result)
     }
 
+    /// Create a function that captures the supplied arguments of a partial application, and takes
+    /// the arguments given as `?` placeholders as its own parameters.
+    fn partially_apply_function(
+        &self,
+        char_index: usize,
+        input: &'a Value<'a>,
+        evaluated_proc: &'a Value<'a>,
+        args: &[Ast],
+        frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
+        let mut evaluated_args = Vec::with_capacity(args.len());
+        for arg in args {
+            evaluated_args.push(match arg.kind {
+                AstKind::PartialArg => None,
+                _ => Some(self.evaluate(arg, input, frame)?),
+            });
+        }
+
+        match evaluated_proc {
+            Value::Lambda {
+                ast,
+                frame: ref lambda_frame,
+                input: lambda_input,
+            } => {
+                if let AstKind::Lambda {
+                    ref name,
+                    ref args,
+                    ref body,
+                    thunk,
+                    ..
+                } = ast.kind
+                {
+                    // Bind the supplied arguments in a new frame, the remaining parameters become
+                    // the parameters of the partially applied lambda
+                    let partial_frame = Frame::new_with_parent(lambda_frame);
+                    let mut params = Vec::new();
+                    for (index, param) in args.iter().enumerate() {
+                        match evaluated_args.get(index) {
+                            Some(None) => params.push(param.clone()),
+                            arg => {
+                                if let AstKind::Var(ref name) = param.kind {
+                                    partial_frame.bind(
+                                        name,
+                                        arg.copied().flatten().unwrap_or_else(Value::undefined),
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    // The signature is dropped, as it describes the parameters of the original
+                    // lambda rather than the remaining ones
+                    let partial = Ast::new(
+                        AstKind::Lambda {
+                            name: name.clone(),
+                            args: params,
+                            body: body.clone(),
+                            thunk,
+                            signature: None,
+                        },
+                        ast.char_index,
+                    );
+
+                    Ok(Value::lambda(
+                        self.arena,
+                        &partial,
+                        lambda_input,
+                        partial_frame,
+                    ))
+                } else {
+                    unreachable!()
+                }
+            }
+
+            proc if proc.is_function() => {
+                // Wrap the function in a lambda which invokes it with the captured arguments
+                // and its own parameters, e.g. $substring(?, 0, 5) becomes
+                // function($0){ $substring($0, 0, 5) }
+                let partial_frame = Frame::new_with_parent(frame);
+                partial_frame.bind("?proc", proc);
+
+                let mut params = Vec::new();
+                let mut call_args = Vec::with_capacity(evaluated_args.len());
+                for (index, arg) in evaluated_args.iter().enumerate() {
+                    let name = format!("?{}", index);
+                    match arg {
+                        Some(arg) => partial_frame.bind(&name, arg),
+                        None => params.push(Ast::new(AstKind::Var(name.clone()), char_index)),
+                    }
+                    call_args.push(Ast::new(AstKind::Var(name), char_index));
+                }
+
+                let name = match proc {
+                    Value::NativeFn { ref name, .. } => name.clone(),
+                    _ => String::new(),
+                };
+
+                let body = Ast::new(
+                    AstKind::Function {
+                        name: name.clone(),
+                        proc: Box::new(Ast::new(AstKind::Var("?proc".to_string()), char_index)),
+                        args: call_args,
+                        is_partial: false,
+                    },
+                    char_index,
+                );
+
+                let partial = Ast::new(
+                    AstKind::Lambda {
+                        name,
+                        args: params,
+                        body: Box::new(body),
+                        thunk: false,
+                        signature: None,
+                    },
+                    char_index,
+                );
+
+                Ok(Value::lambda(
+                    self.arena,
+                    &partial,
+                    Value::undefined(),
+                    partial_frame,
+                ))
+            }
+
+            _ => Err(Error::T1008PartialApplyNonFunction(char_index)),
+        }
+    }
+
     /// Iteratively evaluate a function until a non-function value is returned.
     fn trampoline_evaluate_value(
         &self,
@@ -1259,20 +1644,59 @@ impl<'a> Evaluator<'a> {
         evaluated_proc: &'a Value<'a>,
         evaluated_args: &[&'a Value<'a>],
         frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
+        self.apply_named_function(
+            char_index,
+            None,
+            input,
+            evaluated_proc,
+            evaluated_args,
+            frame,
+        )
+    }
+
+    /// Apply a function that was called by the given name, which is used instead of the
+    /// function's own name when its arguments don't match its signature.
+    fn apply_named_function(
+        &self,
+        char_index: usize,
+        call_name: Option<&str>,
+        input: &'a Value<'a>,
+        evaluated_proc: &'a Value<'a>,
+        evaluated_args: &[&'a Value<'a>],
+        frame: &Frame<'a>,
     ) -> Result<&'a Value<'a>> {
         match evaluated_proc {
             Value::Lambda {
-                ref ast,
-                ref frame,
-                input,
-                ..
+                ast,
+                frame: ref lambda_frame,
+                input: lambda_input,
             } => {
                 if let AstKind::Lambda {
-                    ref body, ref args, ..
+                    ref name,
+                    ref body,
+                    ref args,
+                    ref signature,
+                    ..
                 } = ast.kind
                 {
+                    let validated_args;
+                    let evaluated_args = match signature {
+                        Some(signature) => {
+                            validated_args = signature.validate(
+                                self.arena,
+                                char_index,
+                                call_name.unwrap_or(name),
+                                evaluated_args,
+                                input,
+                            )?;
+                            &validated_args[..]
+                        }
+                        None => evaluated_args,
+                    };
+
                     // Create a new frame for use in the lambda, so it can have locals
-                    let frame = Frame::new_with_parent(frame);
+                    let frame = Frame::new_with_parent(lambda_frame);
 
                     // Bind the arguments to their respective names
                     for (index, arg) in args.iter().enumerate() {
@@ -1290,16 +1714,35 @@ impl<'a> Evaluator<'a> {
                     }
 
                     // Evaluate the lambda!
-                    self.evaluate(body, input, &frame)
+                    self.evaluate(body, lambda_input, &frame)
                 } else {
                     unreachable!()
                 }
             }
             Value::NativeFn {
-                ref name, ref func, ..
+                ref name,
+                ref func,
+                signature,
+                ..
             } => {
+                let validated_args;
+                let evaluated_args = match signature {
+                    Some(signature) => {
+                        validated_args = signature.validate(
+                            self.arena,
+                            char_index,
+                            call_name.unwrap_or(name),
+                            evaluated_args,
+                            input,
+                        )?;
+                        &validated_args[..]
+                    }
+                    None => evaluated_args,
+                };
+
+                self.charge_steps(1)?;
                 let context = self.fn_context(name, char_index, input, frame);
-                func(context, evaluated_args)
+                func.call(context, evaluated_args)
             }
             Value::Transformer {
                 ref pattern,
@@ -1385,3 +1828,17 @@ impl<'a> Evaluator<'a> {
         Ok(result)
     }
 }
+
+/// Perform arithmetic with decimals, returning `None` if the result can't be represented as one.
+fn decimal_arithmetic(op: &BinaryOp, lhs: &Value, rhs: &Value) -> Option<Decimal> {
+    let lhs = lhs.as_decimal()?;
+    let rhs = rhs.as_decimal()?;
+    match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Subtract => lhs.checked_sub(rhs),
+        BinaryOp::Multiply => lhs.checked_mul(rhs),
+        BinaryOp::Divide => lhs.checked_div(rhs),
+        BinaryOp::Modulus => lhs.checked_rem(rhs),
+        _ => unreachable!(),
+    }
+}
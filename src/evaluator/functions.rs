@@ -1,16 +1,22 @@
 use base64::Engine;
 use chrono::{TimeZone, Utc};
-use hashbrown::{DefaultHashBuilder, HashMap};
+use rand::seq::SliceRandom;
 use rand::Rng;
 use regress::{Range, Regex};
+use rust_decimal::{Decimal, RoundingStrategy};
 use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::collections::HashSet;
-use std::time::{SystemTime, UNIX_EPOCH};
-use uuid::Uuid;
+use std::sync::OnceLock;
+use uuid::Builder;
 
 use crate::datetime::{format_custom_date, parse_custom_format, parse_timezone_offset};
 use crate::evaluator::RegexLiteral;
+use crate::integer::{format_integer, parse_integer};
+use crate::number::{format_number, DecimalFormat};
+use crate::parser::ast::Signature;
 use crate::parser::expressions::check_balanced_brackets;
+use crate::parser::parse;
 
 use bumpalo::collections::CollectIn;
 use bumpalo::collections::String as BumpString;
@@ -20,8 +26,8 @@ use bumpalo::Bump;
 use crate::{Error, Result};
 
 use super::frame::Frame;
-use super::value::serialize::{DumpFormatter, PrettyFormatter, Serializer};
-use super::value::{ArrayFlags, Value};
+use super::value::serialize::{DumpFormatter, Formatter, PrettyFormatter, Serializer};
+use super::value::{parse_decimal, ArrayFlags, Value};
 use super::Evaluator;
 
 macro_rules! min_args {
@@ -104,6 +110,12 @@ impl<'a, 'e> FunctionContext<'a, 'e> {
         self.evaluator
             .trampoline_evaluate_value(value, self.input, &self.frame)
     }
+
+    /// Charge steps for work done by the function itself, e.g. comparing values when sorting, in
+    /// addition to the step charged for calling it. This fails if the step limit is exceeded.
+    pub fn charge_steps(&self, steps: usize) -> Result<()> {
+        self.evaluator.charge_steps(steps)
+    }
 }
 
 /// Extend the given values with value.
@@ -139,6 +151,12 @@ pub fn fn_append<'a>(
 
     let arg1_len = if arg1.is_array() { arg1.len() } else { 1 };
     let arg2_len = if arg2.is_array() { arg2.len() } else { 1 };
+    context
+        .evaluator
+        .check_sequence_length(arg1_len + arg2_len)?;
+    context
+        .evaluator
+        .check_memory((arg1_len + arg2_len) * std::mem::size_of::<&Value>())?;
 
     let result = Value::array_with_capacity(
         context.arena,
@@ -176,10 +194,11 @@ pub fn fn_boolean<'a>(
         Value::Undefined => Value::undefined(),
         Value::Null => Value::bool(false),
         Value::Bool(b) => Value::bool(*b),
-        Value::Number(n) => {
+        Value::Number(n) | Value::RawNumber(n, _) => {
             arg.is_valid_number()?;
             Value::bool(*n != 0.0)
         }
+        Value::Decimal(ref d) => Value::bool(!d.is_zero()),
         Value::String(ref str) => Value::bool(!str.is_empty()),
         Value::Object(ref obj) => Value::bool(!obj.is_empty()),
         Value::Array { .. } => match arg.len() {
@@ -235,6 +254,7 @@ pub fn fn_map<'a>(
 
         if !mapped.is_undefined() {
             result.push(mapped);
+            context.evaluator.check_sequence_length(result.len())?;
         }
     }
 
@@ -274,6 +294,7 @@ pub fn fn_filter<'a>(
 
         if include.is_truthy() {
             result.push(item);
+            context.evaluator.check_sequence_length(result.len())?;
         }
     }
 
@@ -304,19 +325,80 @@ pub fn fn_each<'a>(
     assert_arg!(func.is_function(), context, 2);
 
     let result = Value::array(context.arena, ArrayFlags::SEQUENCE);
+    let arity = func.arity();
 
     for (key, value) in obj.entries() {
-        let key = Value::string(context.arena, key);
+        let mut args = vec![*value];
+        if arity >= 2 {
+            args.push(Value::string(context.arena, key));
+        }
+        if arity >= 3 {
+            args.push(obj);
+        }
 
-        let mapped = context.evaluate_function(func, &[value, key])?;
+        let mapped = context.evaluate_function(func, &args)?;
         if !mapped.is_undefined() {
             result.push(mapped);
+            context.evaluator.check_sequence_length(result.len())?;
         }
     }
 
     Ok(result)
 }
 
+pub fn fn_sift<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    let (obj, func) = if args.len() == 1 {
+        let obj_arg = if context.input.is_array() && context.input.has_flags(ArrayFlags::WRAPPED) {
+            &context.input[0]
+        } else {
+            context.input
+        };
+
+        (obj_arg, args[0])
+    } else {
+        (
+            args.first().copied().unwrap_or_else(Value::undefined),
+            args.get(1).copied().unwrap_or_else(Value::undefined),
+        )
+    };
+
+    if obj.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    assert_arg!(obj.is_object(), context, 1);
+    assert_arg!(func.is_function(), context, 2);
+
+    let result = Value::object(context.arena);
+    let arity = func.arity();
+
+    for (key, value) in obj.entries() {
+        let mut args = Vec::new();
+
+        args.push(*value);
+        if arity >= 2 {
+            args.push(Value::string(context.arena, key));
+        }
+        if arity >= 3 {
+            args.push(obj);
+        }
+
+        let include = context.trampoline_evaluate_value(context.evaluate_function(func, &args)?)?;
+        if include.is_truthy() {
+            result.insert(key, value);
+        }
+    }
+
+    if result.entries().len() == 0 {
+        return Ok(Value::undefined());
+    }
+
+    Ok(result)
+}
+
 pub fn fn_keys<'a>(
     context: FunctionContext<'a, '_>,
     args: &[&'a Value<'a>],
@@ -352,6 +434,8 @@ pub fn fn_keys<'a>(
         }
     }
 
+    context.evaluator.check_sequence_length(keys.len())?;
+
     let result = Value::array(context.arena, ArrayFlags::SEQUENCE);
     for key in keys {
         result.push(Value::string(context.arena, &key));
@@ -360,6 +444,66 @@ pub fn fn_keys<'a>(
     Ok(result)
 }
 
+pub fn fn_spread<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arg = args.first().copied().unwrap_or_else(Value::undefined);
+    if !arg.is_array() && !arg.is_object() {
+        return Ok(arg);
+    }
+
+    fn spread_into<'a>(arena: &'a Bump, result: &mut Value<'a>, value: &'a Value<'a>) {
+        if value.is_array() {
+            for member in value.members() {
+                spread_into(arena, result, member);
+            }
+        } else if value.is_object() {
+            for (key, value) in value.entries() {
+                let obj = Value::object_with_capacity(arena, 1);
+                obj.insert(key, value);
+                result.push(obj);
+            }
+        } else if !value.is_undefined() {
+            result.push(value);
+        }
+    }
+
+    let result = Value::array(context.arena, ArrayFlags::SEQUENCE);
+    spread_into(context.arena, result, arg);
+    context.evaluator.check_sequence_length(result.len())?;
+
+    Ok(result)
+}
+
+pub fn fn_type<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arg = args.first().copied().unwrap_or_else(Value::undefined);
+
+    let type_name = match arg {
+        Value::Undefined => return Ok(Value::undefined()),
+        Value::Null => "null",
+        Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..) => "number",
+        Value::String(..) => "string",
+        Value::Bool(..) => "boolean",
+        Value::Array(..) | Value::Range(..) => "array",
+        Value::Object(..) => "object",
+        // Regex literals evaluate to matcher functions in JSONata
+        Value::Regex(..)
+        | Value::Lambda { .. }
+        | Value::NativeFn { .. }
+        | Value::Transformer { .. } => "function",
+    };
+
+    Ok(Value::string(context.arena, type_name))
+}
+
 pub fn fn_merge<'a>(
     context: FunctionContext<'a, '_>,
     args: &[&'a Value<'a>],
@@ -431,15 +575,27 @@ pub fn fn_string<'a>(
         Err(Error::D3001StringNotFinite(context.char_index))
     } else if *pretty == true {
         let serializer = Serializer::new(PrettyFormatter::default(), true);
-        let output = serializer.serialize(input)?;
+        let output = within_memory_limit(&context, serializer).serialize(input)?;
         Ok(Value::string(context.arena, &output))
     } else {
         let serializer = Serializer::new(DumpFormatter, true);
-        let output = serializer.serialize(input)?;
+        let output = within_memory_limit(&context, serializer).serialize(input)?;
         Ok(Value::string(context.arena, &output))
     }
 }
 
+/// Limit the output of a serializer to the memory that's still available, as the output is
+/// copied into the arena afterwards.
+fn within_memory_limit<T: Formatter>(
+    context: &FunctionContext<'_, '_>,
+    serializer: Serializer<T>,
+) -> Serializer<T> {
+    match context.evaluator.available_memory() {
+        Some((available, memory_limit)) => serializer.with_memory_limit(available, memory_limit),
+        None => serializer,
+    }
+}
+
 pub fn fn_substring_before<'a>(
     context: FunctionContext<'a, '_>,
     args: &[&'a Value<'a>],
@@ -725,20 +881,9 @@ pub fn fn_replace<'a>(
 
         // Process replacement based on the replacement_value type
         let replacement_text = match replacement_value {
-            Value::NativeFn { func, .. } => {
-                let match_list = evaluate_match(context.arena, regex, match_str, None);
-
-                let func_result = func(context.clone(), &[match_list])?;
-
-                if let Value::String(ref s) = func_result {
-                    s.as_str().to_string()
-                } else {
-                    return Err(Error::D3012InvalidReplacementType(context.char_index));
-                }
-            }
-
-            func @ Value::Lambda { .. } => {
-                let match_list = evaluate_match(context.arena, regex, match_str, None);
+            func @ (Value::Lambda { .. } | Value::NativeFn { .. }) => {
+                let match_list =
+                    evaluate_match(context.arena, context.evaluator, regex, match_str, None)?;
 
                 let args = &[match_list];
 
@@ -987,6 +1132,8 @@ pub fn fn_split<'a>(
         }
     };
 
+    context.evaluator.check_sequence_length(substrings.len())?;
+
     let result = Value::array_with_capacity(context.arena, substrings.len(), ArrayFlags::empty());
     for substring in &substrings {
         result.push(Value::string(context.arena, substring));
@@ -1006,6 +1153,10 @@ pub fn fn_abs<'a>(
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.abs()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().abs()))
 }
 
@@ -1021,6 +1172,10 @@ pub fn fn_floor<'a>(
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.floor()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().floor()))
 }
 
@@ -1036,9 +1191,25 @@ pub fn fn_ceil<'a>(
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.ceil()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().ceil()))
 }
 
+/// Stands in for a built-in function that the registry doesn't allow, so that it fails however
+/// it's called, including when passed to another function.
+pub fn fn_disabled<'a>(
+    context: FunctionContext<'a, '_>,
+    _args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    Err(Error::T1009DisabledFunction(
+        context.char_index,
+        context.name.to_string(),
+    ))
+}
+
 pub fn fn_lookup_internal<'a>(
     context: FunctionContext<'a, '_>,
     input: &'a Value<'a>,
@@ -1073,7 +1244,11 @@ pub fn fn_lookup<'a>(
     let input = args.first().copied().unwrap_or_else(Value::undefined);
     let key = args.get(1).copied().unwrap_or_else(Value::undefined);
     assert_arg!(key.is_string(), context, 2);
-    Ok(fn_lookup_internal(context.clone(), input, &key.as_str()))
+    let result = fn_lookup_internal(context.clone(), input, &key.as_str());
+    if result.is_array() {
+        context.evaluator.check_sequence_length(result.len())?;
+    }
+    Ok(result)
 }
 
 pub fn fn_count<'a>(
@@ -1113,6 +1288,10 @@ pub fn fn_max<'a>(
         assert_array_of_type!(member.is_number(), context, 1, "number");
         max = f64::max(max, member.as_f64());
     }
+
+    if let Some(max) = decimal_extreme(arr, Ordering::Greater) {
+        return Ok(max);
+    }
     Ok(Value::number(context.arena, max))
 }
 
@@ -1137,6 +1316,10 @@ pub fn fn_min<'a>(
         assert_array_of_type!(member.is_number(), context, 1, "number");
         min = f64::min(min, member.as_f64());
     }
+
+    if let Some(min) = decimal_extreme(arr, Ordering::Less) {
+        return Ok(min);
+    }
     Ok(Value::number(context.arena, min))
 }
 
@@ -1153,15 +1336,83 @@ pub fn fn_sum<'a>(
         return Ok(Value::undefined());
     }
 
-    let arr = Value::wrap_in_array_if_needed(context.arena, arg, ArrayFlags::empty());
+    let sum = numbers_arg(&context, arg)?
+        .into_iter()
+        .fold(0.0, |sum, n| sum + n);
+
+    if let Some(sum) = decimal_sum(arg) {
+        return Ok(Value::decimal(context.arena, sum));
+    }
+    Ok(Value::number(context.arena, sum))
+}
+
+pub fn fn_average<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
 
-    let mut sum = 0.0;
+    let arg = args.first().copied().unwrap_or_else(Value::undefined);
 
+    // $average(undefined) returns undefined
+    if arg.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let numbers = numbers_arg(&context, arg)?;
+
+    // $average([]) returns undefined
+    if numbers.is_empty() {
+        return Ok(Value::undefined());
+    }
+
+    let sum = numbers.iter().fold(0.0, |sum, n| sum + n);
+
+    if let Some(average) =
+        decimal_sum(arg).and_then(|sum| sum.checked_div(Decimal::from(numbers.len())))
+    {
+        return Ok(Value::decimal(context.arena, average));
+    }
+    Ok(Value::number(context.arena, sum / numbers.len() as f64))
+}
+
+/// The exact sum of a number or array of numbers, if any of them are decimals and the sum doesn't
+/// overflow.
+fn decimal_sum<'a>(arg: &'a Value<'a>) -> Option<Decimal> {
+    match arg {
+        Value::Decimal(d) => Some(*d),
+        Value::Array(..) | Value::Range(..) if arg.members().any(Value::is_decimal) => arg
+            .members()
+            .try_fold(Decimal::ZERO, |sum, n| sum.checked_add(n.as_decimal()?)),
+        _ => None,
+    }
+}
+
+/// The largest or smallest of an array of numbers, if any of them are decimals. The number itself
+/// is returned, so that it isn't rounded by converting it to `f64`.
+fn decimal_extreme<'a>(arr: &'a Value<'a>, ordering: Ordering) -> Option<&'a Value<'a>> {
+    if !arr.members().any(Value::is_decimal) {
+        return None;
+    }
+    arr.members()
+        .reduce(|extreme, n| match n.compare_numbers(extreme) {
+            Some(o) if o == ordering => n,
+            _ => extreme,
+        })
+}
+
+/// Validates that the first argument of a numeric aggregate function is a number or an array of
+/// numbers, returning the numbers.
+fn numbers_arg<'a>(context: &FunctionContext<'a, '_>, arg: &'a Value<'a>) -> Result<Vec<f64>> {
+    let arr = Value::wrap_in_array_if_needed(context.arena, arg, ArrayFlags::empty());
+
+    let mut numbers = Vec::with_capacity(arr.len());
     for member in arr.members() {
         assert_array_of_type!(member.is_number(), context, 1, "number");
-        sum += member.as_f64();
+        numbers.push(member.as_f64());
     }
-    Ok(Value::number(context.arena, sum))
+
+    Ok(numbers)
 }
 
 pub fn fn_number<'a>(
@@ -1174,7 +1425,7 @@ pub fn fn_number<'a>(
 
     match arg {
         Value::Undefined => Ok(Value::undefined()),
-        Value::Number(..) => Ok(arg),
+        Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..) => Ok(arg),
         Value::Bool(true) => Ok(Value::number(context.arena, 1)),
         Value::Bool(false) => Ok(Value::number(context.arena, 0)),
         Value::String(s) => {
@@ -1183,6 +1434,11 @@ pub fn fn_number<'a>(
                 .map_err(|_e| Error::D3030NonNumericCast(context.char_index, arg.to_string()))?;
 
             if !result.is_nan() && !result.is_infinite() {
+                if context.evaluator.decimal_numbers() {
+                    if let Some(result) = parse_decimal(s) {
+                        return Ok(Value::decimal(context.arena, result));
+                    }
+                }
                 Ok(Value::number(context.arena, result))
             } else {
                 Ok(Value::undefined())
@@ -1198,7 +1454,7 @@ pub fn fn_random<'a>(
 ) -> Result<&'a Value<'a>> {
     max_args!(context, args, 0);
 
-    let v: f32 = rand::rng().random();
+    let v: f32 = context.evaluator.rng().random();
     Ok(Value::number(context.arena, v))
 }
 
@@ -1208,7 +1464,7 @@ pub fn fn_now<'a>(
 ) -> Result<&'a Value<'a>> {
     max_args!(context, args, 2);
 
-    let now = Utc::now();
+    let now = context.evaluator.now();
 
     let (picture, timezone) = match args {
         [picture, timezone] => (picture.as_str(), timezone.as_str()),
@@ -1336,14 +1592,10 @@ pub fn fn_millis<'a>(
 ) -> Result<&'a Value<'a>> {
     max_args!(context, args, 0);
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards");
-
-    Ok(Value::number_from_u128(
+    Ok(Value::number(
         context.arena,
-        timestamp.as_millis(),
-    )?)
+        context.evaluator.now().timestamp_millis() as f64,
+    ))
 }
 
 pub fn fn_uuid<'a>(
@@ -1352,10 +1604,11 @@ pub fn fn_uuid<'a>(
 ) -> Result<&'a Value<'a>> {
     max_args!(context, args, 0);
 
-    Ok(Value::string(
-        context.arena,
-        Uuid::new_v4().to_string().as_str(),
-    ))
+    let mut bytes = [0; 16];
+    context.evaluator.rng().fill_bytes(&mut bytes);
+    let uuid = Builder::from_random_bytes(bytes).into_uuid();
+
+    Ok(Value::string(context.arena, uuid.to_string().as_str()))
 }
 
 pub fn to_millis<'a>(
@@ -1388,7 +1641,7 @@ pub fn to_millis<'a>(
     };
 
     // Handle different formats using a match handler function
-    match parse_custom_format(&timestamp_str, &picture) {
+    match parse_custom_format(&timestamp_str, &picture, context.evaluator.now()) {
         Some(millis) => Ok(Value::number(context.arena, millis as f64)),
         None => Ok(Value::undefined()),
     }
@@ -1429,14 +1682,14 @@ pub fn fn_zip<'a>(
     }
 
     let min_length = arrays.iter().map(|arr| arr.len()).min().unwrap_or(0);
+    context.evaluator.check_sequence_length(min_length)?;
     let mut iterators: Vec<_> = arrays
         .iter()
         .map(|arr| arr.iter().take(min_length))
         .collect();
 
     // Use an iterator of zipping all the array iterators and collect the result in bumpalo
-    let result: bumpalo::collections::Vec<&Value<'a>> = std::iter::repeat(())
-        .take(min_length)
+    let result: bumpalo::collections::Vec<&Value<'a>> = std::iter::repeat_n((), min_length)
         .map(|_| {
             let zipped: bumpalo::collections::Vec<&Value<'a>> = iterators
                 .iter_mut()
@@ -1496,11 +1749,18 @@ pub fn single<'a>(
     if let Value::Array(elements, _) = arr {
         let mut result: Option<&'a Value<'a>> = None;
 
+        let arity = func.arity();
+
         for (index, entry) in elements.iter().enumerate() {
-            let res = context.evaluate_function(
-                func,
-                &[entry, Value::number(context.arena, index as f64), arr],
-            )?;
+            let mut args = vec![*entry];
+            if arity >= 2 {
+                args.push(Value::number(context.arena, index as f64));
+            }
+            if arity >= 3 {
+                args.push(arr);
+            }
+
+            let res = context.evaluate_function(func, &args)?;
 
             if res.as_bool() {
                 if result.is_some() {
@@ -1638,12 +1898,41 @@ pub fn fn_reverse<'a>(
     }
 
     assert_arg!(arr.is_array(), context, 1);
+    context.evaluator.check_sequence_length(arr.len())?;
 
     let result = Value::array_with_capacity(context.arena, arr.len(), ArrayFlags::empty());
     arr.members().rev().for_each(|member| result.push(member));
     Ok(result)
 }
 
+pub fn fn_shuffle<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arg = args.first().copied().unwrap_or_else(Value::undefined);
+    if arg.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let arr = Value::wrap_in_array_if_needed(context.arena, arg, ArrayFlags::empty());
+    if arr.len() <= 1 {
+        return Ok(arr);
+    }
+    context.evaluator.check_sequence_length(arr.len())?;
+
+    let mut members: Vec<_> = arr.members().collect();
+    members.shuffle(&mut *context.evaluator.rng());
+
+    let result = Value::array_with_capacity(context.arena, members.len(), ArrayFlags::empty());
+    for member in members {
+        result.push(member);
+    }
+
+    Ok(result)
+}
+
 #[allow(clippy::mutable_key_type)]
 pub fn fn_distinct<'a>(
     context: FunctionContext<'a, '_>,
@@ -1656,6 +1945,9 @@ pub fn fn_distinct<'a>(
         return Ok(arr);
     }
 
+    context.charge_steps(arr.len())?;
+    context.evaluator.check_sequence_length(arr.len())?;
+
     let result = Value::array_with_capacity(context.arena, arr.len(), ArrayFlags::empty());
     let mut set = HashSet::new();
     for member in arr.members() {
@@ -1699,9 +1991,17 @@ pub fn fn_join<'a>(
         "".into()
     };
 
-    let mut result = String::with_capacity(1024);
-    for (index, member) in strings.members().enumerate() {
+    let mut length = separator
+        .len()
+        .saturating_mul(strings.len().saturating_sub(1));
+    for member in strings.members() {
         assert_array_of_type!(member.is_string(), context, 1, "string");
+        length = length.saturating_add(member.as_str().len());
+    }
+    context.evaluator.check_memory(length)?;
+
+    let mut result = String::with_capacity(length);
+    for (index, member) in strings.members().enumerate() {
         result.push_str(member.as_str().borrow());
         if index != strings.len() - 1 {
             result.push_str(&separator);
@@ -1734,12 +2034,19 @@ pub fn fn_sort<'a, 'e>(
     // TODO: This is all a bit inefficient, copying Vecs of references around, but
     // at least it's just references.
 
+    // A merge sort makes up to n * log2(n) comparisons
+    let length = arr.len();
+    context.charge_steps(length * (usize::BITS - length.leading_zeros()) as usize)?;
+    context.evaluator.check_sequence_length(length)?;
+
     let unsorted = arr.members().collect::<Vec<&'a Value<'a>>>();
     let sorted = if args.get(1).is_none() {
         merge_sort(
             unsorted,
             &|a: &'a Value<'a>, b: &'a Value<'a>| match (a, b) {
-                (Value::Number(a), Value::Number(b)) => Ok(a > b),
+                _ if a.is_number() && b.is_number() => {
+                    Ok(a.compare_numbers(b) == Some(Ordering::Greater))
+                }
                 (Value::String(a), Value::String(b)) => Ok(a > b),
                 _ => Err(Error::D3070InvalidDefaultSort(context.char_index)),
             },
@@ -1848,6 +2155,134 @@ pub fn fn_base64_decode<'a>(
     Ok(Value::string(context.arena, &decoded))
 }
 
+/// Characters left unescaped by `$encodeUrlComponent`, matching JavaScript's `encodeURIComponent`.
+const URL_COMPONENT_UNESCAPED: &str = "-_.!~*'()";
+
+/// Characters that `$encodeUrl` additionally leaves unescaped, and that `$decodeUrl` leaves
+/// escaped, matching JavaScript's `encodeURI` and `decodeURI`.
+const URL_RESERVED: &str = ";,/?:@&=+$#";
+
+fn encode_url(value: &str, unescaped: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || unescaped.as_bytes().contains(&byte) {
+            result.push(byte as char);
+        } else {
+            result.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    result
+}
+
+/// Decodes the percent-escapes in `value`, leaving escapes for any of the `reserved` characters
+/// as they are. Returns `None` if an escape is truncated, or the escaped bytes are not valid UTF-8.
+fn decode_url(value: &str, reserved: &str) -> Option<String> {
+    fn escaped_byte(bytes: &[u8], index: usize) -> Option<u8> {
+        if bytes.get(index) != Some(&b'%') {
+            return None;
+        }
+        let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+        u8::from_str_radix(hex, 16).ok()
+    }
+
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'%' {
+            result.push(bytes[index]);
+            index += 1;
+            continue;
+        }
+
+        let lead = escaped_byte(bytes, index)?;
+        let len = match lead {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return None,
+        };
+
+        if len == 1 {
+            if reserved.as_bytes().contains(&lead) {
+                result.extend_from_slice(&bytes[index..index + 3]);
+            } else {
+                result.push(lead);
+            }
+            index += 3;
+            continue;
+        }
+
+        let mut sequence = vec![lead];
+        for n in 1..len {
+            sequence.push(escaped_byte(bytes, index + n * 3)?);
+        }
+        result.extend_from_slice(std::str::from_utf8(&sequence).ok()?.as_bytes());
+        index += len * 3;
+    }
+
+    String::from_utf8(result).ok()
+}
+
+fn url_function<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+    transform: impl FnOnce(&str) -> Option<String>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+    let arg = args.first().copied().unwrap_or_else(Value::undefined);
+    if arg.is_undefined() {
+        return Ok(Value::undefined());
+    }
+    assert_arg!(arg.is_string(), context, 1);
+
+    let value = arg.as_str();
+    match transform(&value) {
+        Some(result) => Ok(Value::string(context.arena, &result)),
+        None => Err(Error::D3140MalformedUrl(
+            context.char_index,
+            context.name.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+pub fn fn_encode_url<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    url_function(context, args, |value| {
+        Some(encode_url(
+            value,
+            &format!("{}{}", URL_COMPONENT_UNESCAPED, URL_RESERVED),
+        ))
+    })
+}
+
+pub fn fn_encode_url_component<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    url_function(context, args, |value| {
+        Some(encode_url(value, URL_COMPONENT_UNESCAPED))
+    })
+}
+
+pub fn fn_decode_url<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    url_function(context, args, |value| decode_url(value, URL_RESERVED))
+}
+
+pub fn fn_decode_url_component<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    url_function(context, args, |value| decode_url(value, ""))
+}
+
 pub fn fn_round<'a>(
     context: FunctionContext<'a, '_>,
     args: &[&'a Value<'a>],
@@ -1866,6 +2301,12 @@ pub fn fn_round<'a>(
         0
     };
 
+    if let Value::Decimal(d) = number {
+        if let Some(result) = round_decimal(*d, precision) {
+            return Ok(Value::decimal(context.arena, result));
+        }
+    }
+
     let num = multiply_by_pow10(number.as_f64(), precision)?;
     let num = num.round_ties_even();
     let num = multiply_by_pow10(num, -precision)?;
@@ -1873,6 +2314,165 @@ pub fn fn_round<'a>(
     Ok(Value::number(context.arena, num))
 }
 
+pub fn fn_format_number<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 3);
+
+    let value = args.first().copied().unwrap_or_else(Value::undefined);
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let picture = args.get(1).copied().unwrap_or_else(Value::undefined);
+    let options = args.get(2).copied().unwrap_or_else(Value::undefined);
+
+    assert_arg!(value.is_number(), context, 1);
+    assert_arg!(picture.is_string(), context, 2);
+    assert_arg!(options.is_undefined() || options.is_object(), context, 3);
+
+    let mut format = DecimalFormat::default();
+    if options.is_object() {
+        for (property, value) in options.entries() {
+            assert_arg!(value.is_string(), context, 3);
+            format.set(property, &value.as_str());
+        }
+    }
+
+    let result = format_number(value.as_f64(), &picture.as_str(), &format)?;
+
+    Ok(Value::string(context.arena, &result))
+}
+
+pub fn fn_format_integer<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 2);
+
+    let value = args.first().copied().unwrap_or_else(Value::undefined);
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let picture = args.get(1).copied().unwrap_or_else(Value::undefined);
+
+    assert_arg!(value.is_number(), context, 1);
+    assert_arg!(picture.is_string(), context, 2);
+
+    let result = format_integer(value.as_f64(), &picture.as_str())?;
+
+    Ok(Value::string(context.arena, &result))
+}
+
+pub fn fn_parse_integer<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 2);
+
+    let value = args.first().copied().unwrap_or_else(Value::undefined);
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let picture = args.get(1).copied().unwrap_or_else(Value::undefined);
+
+    assert_arg!(value.is_string(), context, 1);
+    assert_arg!(picture.is_string(), context, 2);
+
+    match parse_integer(&value.as_str(), &picture.as_str())? {
+        Some(result) => Ok(Value::number(context.arena, result)),
+        None => Ok(Value::undefined()),
+    }
+}
+
+pub fn fn_format_base<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 2);
+
+    let value = args.first().copied().unwrap_or_else(Value::undefined);
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let radix = args.get(1).copied().unwrap_or_else(Value::undefined);
+
+    assert_arg!(value.is_number(), context, 1);
+    assert_arg!(radix.is_undefined() || radix.is_number(), context, 2);
+
+    let value = value.as_f64().round_ties_even();
+    let radix = if radix.is_undefined() {
+        10.0
+    } else {
+        radix.as_f64().round_ties_even()
+    };
+
+    if !(2.0..=36.0).contains(&radix) {
+        return Err(Error::D3100RadixOutOfRange(context.char_index, radix));
+    }
+
+    if !value.is_finite() {
+        return Err(Error::D3001StringNotFinite(context.char_index));
+    }
+
+    Ok(Value::string(
+        context.arena,
+        &format_radix(value, radix as u32),
+    ))
+}
+
+/// Write out a whole number in the given radix. The digits are worked out exactly, however
+/// large the number is.
+fn format_radix(value: f64, radix: u32) -> String {
+    // A float is its mantissa times a power of two, which as an integer is held in 32-bit limbs,
+    // least significant first
+    let bits = value.abs().to_bits();
+    let exponent = (bits >> 52) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    let (mantissa, shift) = if exponent == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), exponent - 1075)
+    };
+    let mut limbs = if shift < 0 {
+        let whole = mantissa.checked_shr(shift.unsigned_abs()).unwrap_or(0);
+        vec![whole as u32, (whole >> 32) as u32]
+    } else {
+        let mut limbs = vec![0; shift as usize / 32];
+        let whole = (mantissa as u128) << (shift % 32);
+        limbs.extend([whole as u32, (whole >> 32) as u32, (whole >> 64) as u32]);
+        limbs
+    };
+
+    let radix_u64 = radix as u64;
+    let mut digits = Vec::new();
+    loop {
+        let mut remainder = 0;
+        for limb in limbs.iter_mut().rev() {
+            let current = (remainder << 32) | *limb as u64;
+            *limb = (current / radix_u64) as u32;
+            remainder = current % radix_u64;
+        }
+        digits.push(char::from_digit(remainder as u32, radix).unwrap_or('0'));
+
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        if limbs.is_empty() {
+            break;
+        }
+    }
+    if value < 0.0 {
+        digits.push('-');
+    }
+
+    digits.iter().rev().collect()
+}
+
 fn is_array_of_strings(value: &Value) -> bool {
     if let Value::Array(elements, _) = value {
         elements.iter().all(|v| v.is_string())
@@ -1935,29 +2535,46 @@ pub fn fn_reduce<'a>(
         1
     };
 
+    let arity = func.arity();
+
     for (index, value) in elements[start_index..].iter().enumerate() {
-        let index_value = Value::number(context.arena, index as f64);
+        let mut args = vec![accumulator, *value];
+        if arity >= 3 {
+            args.push(Value::number(context.arena, index as f64));
+        }
+        if arity >= 4 {
+            args.push(original_value);
+        }
 
-        let result =
-            context.evaluate_function(func, &[accumulator, value, index_value, original_value]);
+        let new_accumulator = context.evaluate_function(func, &args)?;
 
-        match result {
-            Ok(new_accumulator) => {
-                // If the result is a thunk, let's evaluate it so it's ready for the next iteration
-                accumulator = context.trampoline_evaluate_value(new_accumulator)?;
-            }
-            Err(_) => {
-                return Err(Error::T0410ArgumentNotValid(1, 1, context.name.to_string()));
-            }
-        }
+        // If the result is a thunk, let's evaluate it so it's ready for the next iteration
+        accumulator = context.trampoline_evaluate_value(new_accumulator)?;
     }
 
     Ok(accumulator)
 }
 
+/// Round a decimal to the given number of decimal places, using the same round half to even
+/// strategy as numbers. A negative precision rounds to a power of ten.
+fn round_decimal(number: Decimal, precision: isize) -> Option<Decimal> {
+    if precision >= 0 {
+        let precision = u32::try_from(precision).ok()?;
+        return Some(
+            number.round_dp_with_strategy(precision, RoundingStrategy::MidpointNearestEven),
+        );
+    }
+
+    let scale = Decimal::from_scientific(&format!("1e{}", -precision)).ok()?;
+    let rounded = number
+        .checked_div(scale)?
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointNearestEven);
+    rounded.checked_mul(scale)
+}
+
 // We need to do this multiplication by powers of 10 in a string to avoid
 // floating point precision errors which will affect the rounding algorithm
-fn multiply_by_pow10(num: f64, pow: isize) -> Result<f64> {
+pub(crate) fn multiply_by_pow10(num: f64, pow: isize) -> Result<f64> {
     let num_str = format!("{}e{}", num, pow);
     num_str
         .parse::<f64>()
@@ -1997,6 +2614,11 @@ pub fn fn_pad<'a>(
         return Ok(Value::string(context.arena, &str_to_pad));
     }
 
+    // Each padding character takes at most as many bytes as the whole padding string
+    context
+        .evaluator
+        .check_memory(pad_length.saturating_mul(pad_char.len()) + str_to_pad.len())?;
+
     let padding = pad_char
         .chars()
         .cycle()
@@ -2046,12 +2668,13 @@ pub fn fn_match<'a>(
         }
     });
 
-    Ok(evaluate_match(
+    evaluate_match(
         context.arena,
+        context.evaluator,
         regex_literal.get_regex(),
         &value_to_validate.as_str(),
         limit,
-    ))
+    )
 }
 
 /// An inner helper which evaluates the `$match` function.
@@ -2077,16 +2700,13 @@ pub fn fn_match<'a>(
 /// ]
 fn evaluate_match<'a>(
     arena: &'a Bump,
+    evaluator: &Evaluator<'a>,
     regex: &Regex,
     input_str: &str,
     limit: Option<usize>,
-) -> &'a Value<'a> {
+) -> Result<&'a Value<'a>> {
     let limit = limit.unwrap_or(usize::MAX);
 
-    let key_match = BumpString::from_str_in("match", arena);
-    let key_index = BumpString::from_str_in("index", arena);
-    let key_groups = BumpString::from_str_in("groups", arena);
-
     let mut matches: bumpalo::collections::Vec<&Value<'a>> =
         bumpalo::collections::Vec::new_in(arena);
 
@@ -2113,14 +2733,145 @@ fn evaluate_match<'a>(
 
         let groups_val = arena.alloc(Value::Array(group_vec, ArrayFlags::empty()));
 
-        let mut match_obj: HashMap<BumpString, &Value<'a>, DefaultHashBuilder, &Bump> =
-            HashMap::with_capacity_and_hasher_in(3, DefaultHashBuilder::default(), arena);
-        match_obj.insert(key_match.clone(), match_str);
-        match_obj.insert(key_index.clone(), index_val);
-        match_obj.insert(key_groups.clone(), groups_val);
+        let match_obj = Value::object_with_capacity(arena, 3);
+        match_obj.insert("match", match_str);
+        match_obj.insert("index", index_val);
+        match_obj.insert("groups", groups_val);
 
-        matches.push(arena.alloc(Value::Object(match_obj)));
+        matches.push(match_obj);
+        evaluator.check_sequence_length(matches.len())?;
     }
 
-    arena.alloc(Value::Array(matches, ArrayFlags::empty()))
+    Ok(arena.alloc(Value::Array(matches, ArrayFlags::empty())))
+}
+
+pub fn fn_eval<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &[&'a Value<'a>],
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 2);
+
+    let expr = args.first().copied().unwrap_or_else(Value::undefined);
+    if expr.is_undefined() {
+        return Ok(Value::undefined());
+    }
+    assert_arg!(expr.is_string(), context, 1);
+
+    let (ast, _) = parse(&expr.as_str())
+        .map_err(|e| Error::D3120EvalSyntaxError(context.char_index, e.to_string()))?;
+
+    // If a focus is given and it's an array, wrap it so that it gets treated as a single input
+    let input = match args.get(1).copied() {
+        Some(focus) if focus.is_array() && !focus.has_flags(ArrayFlags::SEQUENCE) => {
+            Value::wrap_in_array(context.arena, focus, ArrayFlags::WRAPPED)
+        }
+        Some(focus) if !focus.is_undefined() => focus,
+        _ => context.input,
+    };
+
+    let frame = Frame::new_with_parent(&context.frame);
+    context
+        .evaluator
+        .evaluate(&ast, input, &frame)
+        .map_err(|e| match e {
+            // Limit errors are reported as they are, as they apply to the whole evaluation
+            Error::U1001StackOverflow
+            | Error::U1001Timeout
+            | Error::U1002MemoryLimit(..)
+            | Error::U1002SequenceTooLong(..)
+            | Error::U1003StepLimit(..) => e,
+            e => Error::D3121EvalError(context.char_index, e.to_string()),
+        })
+}
+
+/// A built-in function implementation.
+pub type BuiltinFn =
+    for<'a, 'e> fn(FunctionContext<'a, 'e>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>;
+
+/// The built-in functions, with their names, arities and signatures. The signatures are the same
+/// as in the reference implementation, and arguments are validated against them before each call.
+pub static BUILTINS: &[(&str, usize, &str, BuiltinFn)] = &[
+    ("abs", 1, "<n-:n>", fn_abs),
+    ("append", 2, "<xx:a>", fn_append),
+    ("average", 1, "<a<n>:n>", fn_average),
+    ("assert", 2, "<bs?:x>", fn_assert),
+    ("base64decode", 1, "<s-:s>", fn_base64_decode),
+    ("base64encode", 1, "<s-:s>", fn_base64_encode),
+    ("boolean", 1, "<x-:b>", fn_boolean),
+    ("ceil", 1, "<n-:n>", fn_ceil),
+    ("contains", 2, "<s-(sf):b>", fn_contains),
+    ("count", 1, "<a:n>", fn_count),
+    ("decodeUrl", 1, "<s-:s>", fn_decode_url),
+    ("decodeUrlComponent", 1, "<s-:s>", fn_decode_url_component),
+    ("distinct", 1, "<x:x>", fn_distinct),
+    ("each", 2, "<o-f:a>", fn_each),
+    ("encodeUrl", 1, "<s-:s>", fn_encode_url),
+    ("encodeUrlComponent", 1, "<s-:s>", fn_encode_url_component),
+    ("error", 1, "<s?:x>", fn_error),
+    ("eval", 2, "<sx?:x>", fn_eval),
+    ("exists", 1, "<x:b>", fn_exists),
+    ("fromMillis", 3, "<n-s?s?:s>", from_millis),
+    ("toMillis", 2, "<s-s?:n>", to_millis),
+    ("sift", 2, "<o-f?:o>", fn_sift),
+    ("single", 2, "<af?>", single),
+    ("filter", 2, "<af>", fn_filter),
+    ("floor", 1, "<n-:n>", fn_floor),
+    ("formatBase", 2, "<n-n?:s>", fn_format_base),
+    ("formatInteger", 2, "<n-s:s>", fn_format_integer),
+    ("formatNumber", 3, "<n-so?:s>", fn_format_number),
+    ("join", 2, "<a<s>s?:s>", fn_join),
+    ("keys", 1, "<x-:a<s>>", fn_keys),
+    ("length", 1, "<s-:n>", fn_length),
+    ("lookup", 2, "<x-s:x>", fn_lookup),
+    ("lowercase", 1, "<s-:s>", fn_lowercase),
+    ("map", 2, "<af>", fn_map),
+    ("match", 2, "<s-f<s:o>n?:a<o>>", fn_match),
+    ("max", 1, "<a<n>:n>", fn_max),
+    ("merge", 1, "<a<o>:o>", fn_merge),
+    ("min", 1, "<a<n>:n>", fn_min),
+    ("not", 1, "<x-:b>", fn_not),
+    ("now", 2, "<s?s?:s>", fn_now),
+    ("number", 1, "<(nsb)-:n>", fn_number),
+    ("pad", 2, "<s-ns?:s>", fn_pad),
+    ("parseInteger", 2, "<s-s:n>", fn_parse_integer),
+    ("power", 2, "<n-n:n>", fn_power),
+    ("random", 0, "<:n>", fn_random),
+    ("reduce", 3, "<afj?:j>", fn_reduce),
+    ("replace", 4, "<s-(sf)(sf)n?:s>", fn_replace),
+    ("reverse", 1, "<a:a>", fn_reverse),
+    ("shuffle", 1, "<a:a>", fn_shuffle),
+    ("round", 2, "<n-n?:n>", fn_round),
+    ("sort", 2, "<af?:a>", fn_sort),
+    ("split", 3, "<s-(sf)n?:a<s>>", fn_split),
+    ("spread", 1, "<x-:a<o>>", fn_spread),
+    ("sqrt", 1, "<n-:n>", fn_sqrt),
+    ("string", 1, "<x-b?:s>", fn_string),
+    ("substring", 3, "<s-nn?:s>", fn_substring),
+    ("substringBefore", 2, "<s-s:s>", fn_substring_before),
+    ("substringAfter", 2, "<s-s:s>", fn_substring_after),
+    ("sum", 1, "<a<n>:n>", fn_sum),
+    ("trim", 1, "<s-:s>", fn_trim),
+    ("type", 1, "<x:s>", fn_type),
+    ("uppercase", 1, "<s-:s>", fn_uppercase),
+    ("zip", 1, "<a+>", fn_zip),
+    ("millis", 0, "<:n>", fn_millis),
+    ("uuid", 0, "<:s>", fn_uuid),
+];
+
+/// Look up a built-in function by name, with its parsed signature.
+pub fn builtin(name: &str) -> Option<(&'static str, usize, &'static Signature, BuiltinFn)> {
+    static SIGNATURES: OnceLock<Vec<Signature>> = OnceLock::new();
+
+    let index = BUILTINS.iter().position(|(builtin, ..)| *builtin == name)?;
+    let signatures = SIGNATURES.get_or_init(|| {
+        BUILTINS
+            .iter()
+            .map(|(_, _, signature, _)| {
+                Signature::parse(signature, 0).expect("Built-in signatures should be valid")
+            })
+            .collect()
+    });
+
+    let (name, arity, _, func) = BUILTINS[index];
+    Some((name, arity, &signatures[index], func))
 }
@@ -1,23 +1,28 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
 
 use bitflags::bitflags;
 use bumpalo::boxed::Box;
 use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
-use hashbrown::DefaultHashBuilder;
-use hashbrown::HashMap;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 
 use super::frame::Frame;
 use super::functions::FunctionContext;
-use crate::parser::ast::{Ast, AstKind, RegexLiteral};
+use crate::parser::ast::{Ast, AstKind, RegexLiteral, Signature};
 use crate::{Error, Result};
 
+mod de;
 pub mod impls;
 pub mod iterator;
+pub mod object;
 mod range;
+mod ser;
 pub mod serialize;
 
+use self::object::ObjectMap;
 use self::range::Range;
 use self::serialize::{DumpFormatter, PrettyFormatter, Serializer};
 pub use iterator::MemberIterator;
@@ -33,6 +38,33 @@ bitflags! {
     }
 }
 
+/// A custom function implemented as a closure.
+pub type CustomFn<'a> =
+    dyn Fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>> + 'a;
+
+/// The implementation of a native function.
+#[derive(Clone, Copy)]
+pub enum NativeFunction<'a> {
+    /// A plain function pointer, which is how the built-in functions are implemented.
+    Fn(fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>),
+
+    /// A closure, for custom functions that need to capture configuration or other state.
+    Closure(&'a CustomFn<'a>),
+}
+
+impl<'a> NativeFunction<'a> {
+    pub fn call(
+        &self,
+        context: FunctionContext<'a, '_>,
+        args: &[&'a Value<'a>],
+    ) -> Result<&'a Value<'a>> {
+        match *self {
+            NativeFunction::Fn(func) => func(context, args),
+            NativeFunction::Closure(func) => func(context, args),
+        }
+    }
+}
+
 pub const UNDEFINED: Value = Value::Undefined;
 pub const TRUE: Value = Value::Bool(true);
 pub const FALSE: Value = Value::Bool(false);
@@ -49,11 +81,17 @@ pub enum Value<'a> {
     Undefined,
     Null,
     Number(f64),
+    /// An exact decimal number, which is only created when decimal numbers are enabled.
+    Decimal(Decimal),
+    /// A number read from the input along with its original text, which is written out instead of
+    /// formatting the number if it is passed through unchanged. This is only created when number
+    /// lexemes are preserved, and otherwise behaves exactly like `Number`.
+    RawNumber(f64, &'a str),
     Bool(bool),
     String(BumpString<'a>),
     Regex(std::boxed::Box<RegexLiteral>),
     Array(BumpVec<'a, &'a Value<'a>>, ArrayFlags),
-    Object(HashMap<BumpString<'a>, &'a Value<'a>, DefaultHashBuilder, &'a Bump>),
+    Object(ObjectMap<'a>),
     Range(Range<'a>),
     Lambda {
         ast: Box<'a, Ast>,
@@ -63,7 +101,8 @@ pub enum Value<'a> {
     NativeFn {
         name: String,
         arity: usize,
-        func: fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>,
+        func: NativeFunction<'a>,
+        signature: Option<&'a Signature>,
     },
     Transformer {
         pattern: std::boxed::Box<Ast>,
@@ -80,7 +119,7 @@ impl<'a> Value<'a> {
         unsafe { std::mem::transmute::<&Value<'static>, &'a Value<'a>>(&UNDEFINED) }
     }
 
-    pub fn null(arena: &Bump) -> &mut Value {
+    pub fn null(arena: &Bump) -> &mut Value<'_> {
         arena.alloc(Value::Null)
     }
 
@@ -92,11 +131,20 @@ impl<'a> Value<'a> {
         }
     }
 
-    pub fn number(arena: &Bump, value: impl Into<f64>) -> &mut Value {
+    pub fn number(arena: &Bump, value: impl Into<f64>) -> &mut Value<'_> {
         arena.alloc(Value::Number(value.into()))
     }
 
-    pub fn number_from_u128(arena: &Bump, value: u128) -> Result<&mut Value> {
+    pub fn decimal(arena: &Bump, value: Decimal) -> &mut Value<'_> {
+        arena.alloc(Value::Decimal(value))
+    }
+
+    /// A number along with the text it was read from, which is copied into the arena.
+    pub fn raw_number(arena: &'a Bump, value: f64, lexeme: &str) -> &'a mut Value<'a> {
+        arena.alloc(Value::RawNumber(value, arena.alloc_str(lexeme)))
+    }
+
+    pub fn number_from_u128(arena: &Bump, value: u128) -> Result<&mut Value<'_>> {
         let value_f64 = value as f64;
         if value_f64 as u128 != value {
             // number is too large to retain precision
@@ -109,7 +157,18 @@ impl<'a> Value<'a> {
         arena.alloc(Value::String(BumpString::from_str_in(value, arena)))
     }
 
-    pub fn array(arena: &Bump, flags: ArrayFlags) -> &mut Value {
+    /// Create a value from any serializable Rust value, in the same way as
+    /// `serde_json::to_value`.
+    pub fn from_serialize<T: serde::Serialize + ?Sized>(
+        arena: &'a Bump,
+        value: &T,
+    ) -> Result<&'a Value<'a>> {
+        value
+            .serialize(ser::ValueSerializer::new(arena))
+            .map_err(|e| Error::T1011NotSerializable(e.to_string()))
+    }
+
+    pub fn array(arena: &Bump, flags: ArrayFlags) -> &mut Value<'_> {
         let v = BumpVec::new_in(arena);
         arena.alloc(Value::Array(v, flags))
     }
@@ -122,30 +181,27 @@ impl<'a> Value<'a> {
         arena.alloc(Value::Array(arr, flags))
     }
 
-    pub fn array_with_capacity(arena: &Bump, capacity: usize, flags: ArrayFlags) -> &mut Value {
+    pub fn array_with_capacity(arena: &Bump, capacity: usize, flags: ArrayFlags) -> &mut Value<'_> {
         arena.alloc(Value::Array(
             BumpVec::with_capacity_in(capacity, arena),
             flags,
         ))
     }
 
-    pub fn object(arena: &Bump) -> &mut Value {
-        arena.alloc(Value::Object(HashMap::new_in(arena)))
+    pub fn object(arena: &Bump) -> &mut Value<'_> {
+        arena.alloc(Value::Object(ObjectMap::new_in(arena)))
     }
 
-    pub fn object_from<H>(
-        hash: &HashMap<BumpString<'a>, &'a Value<'a>, H, &'a Bump>,
-        arena: &'a Bump,
-    ) -> &'a mut Value<'a> {
-        let result = Value::object_with_capacity(arena, hash.len());
+    pub fn object_from(object: &ObjectMap<'a>, arena: &'a Bump) -> &'a mut Value<'a> {
+        let result = Value::object_with_capacity(arena, object.len());
         if let Value::Object(o) = result {
-            o.extend(hash.iter().map(|(k, v)| (k.clone(), *v)));
+            o.extend(object.iter().map(|(k, v)| (k.clone(), *v)));
         }
         result
     }
 
-    pub fn object_with_capacity(arena: &Bump, capacity: usize) -> &mut Value {
-        arena.alloc(Value::Object(HashMap::with_capacity_in(capacity, arena)))
+    pub fn object_with_capacity(arena: &Bump, capacity: usize) -> &mut Value<'_> {
+        arena.alloc(Value::Object(ObjectMap::with_capacity_in(capacity, arena)))
     }
 
     pub fn lambda(
@@ -170,7 +226,43 @@ impl<'a> Value<'a> {
         arena.alloc(Value::NativeFn {
             name: name.to_string(),
             arity,
-            func,
+            func: NativeFunction::Fn(func),
+            signature: None,
+        })
+    }
+
+    /// Create a native function whose arguments are validated against a signature before each
+    /// call. Unlike for closures, the arity is given separately, as it's the number of arguments
+    /// that higher-order functions like `$map` pass, which can be fewer than the signature allows.
+    pub fn nativefn_with_signature(
+        arena: &'a Bump,
+        name: &str,
+        arity: usize,
+        signature: &'a Signature,
+        func: fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>,
+    ) -> &'a mut Value<'a> {
+        arena.alloc(Value::NativeFn {
+            name: name.to_string(),
+            arity,
+            func: NativeFunction::Fn(func),
+            signature: Some(signature),
+        })
+    }
+
+    /// Create a native function implemented by a closure. If a signature is given, it determines
+    /// the arity of the function and arguments are validated against it before each call.
+    pub fn closure(
+        arena: &'a Bump,
+        name: &str,
+        arity: usize,
+        signature: Option<&'a Signature>,
+        func: &'a CustomFn<'a>,
+    ) -> &'a mut Value<'a> {
+        arena.alloc(Value::NativeFn {
+            name: name.to_string(),
+            arity: signature.map_or(arity, Signature::len),
+            func: NativeFunction::Closure(func),
+            signature,
         })
     }
 
@@ -208,12 +300,19 @@ impl<'a> Value<'a> {
     }
 
     pub fn is_number(&self) -> bool {
-        matches!(&self, Value::Number(..))
+        matches!(
+            &self,
+            Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..)
+        )
+    }
+
+    pub fn is_decimal(&self) -> bool {
+        matches!(&self, Value::Decimal(..))
     }
 
     pub fn is_integer(&self) -> bool {
         match self {
-            Value::Number(n) => match n.classify() {
+            Value::Number(n) | Value::RawNumber(n, _) => match n.classify() {
                 std::num::FpCategory::Nan
                 | std::num::FpCategory::Infinite
                 | std::num::FpCategory::Subnormal => false,
@@ -222,6 +321,7 @@ impl<'a> Value<'a> {
                     n - mantissa == 0.0
                 }
             },
+            Value::Decimal(d) => d.is_integer(),
             _ => false,
         }
     }
@@ -256,7 +356,7 @@ impl<'a> Value<'a> {
 
     pub fn is_valid_number(&self) -> Result<bool> {
         match self {
-            Value::Number(n) => {
+            Value::Number(n) | Value::RawNumber(n, _) => {
                 if n.is_nan() {
                     Ok(false)
                 } else if n.is_infinite() {
@@ -265,6 +365,7 @@ impl<'a> Value<'a> {
                     Ok(true)
                 }
             }
+            Value::Decimal(..) => Ok(true),
             _ => Ok(false),
         }
     }
@@ -275,7 +376,8 @@ impl<'a> Value<'a> {
 
     pub fn is_finite(&self) -> bool {
         match self {
-            Value::Number(n) => n.is_finite(),
+            Value::Number(n) | Value::RawNumber(n, _) => n.is_finite(),
+            Value::Decimal(..) => true,
             _ => false,
         }
     }
@@ -303,7 +405,8 @@ impl<'a> Value<'a> {
         match *self {
             Value::Undefined => false,
             Value::Null => false,
-            Value::Number(n) => n != 0.0,
+            Value::Number(n) | Value::RawNumber(n, _) => n != 0.0,
+            Value::Decimal(ref d) => !d.is_zero(),
             Value::Bool(ref b) => *b,
             Value::String(ref s) => !s.is_empty(),
             Value::Array(ref a, _) => match a.len() {
@@ -342,7 +445,7 @@ impl<'a> Value<'a> {
         }
     }
 
-    pub fn entries(&self) -> hashbrown::hash_map::Iter<'_, BumpString<'a>, &'a Value> {
+    pub fn entries(&self) -> object::Iter<'_, 'a> {
         match self {
             Value::Object(map) => map.iter(),
             _ => panic!("Not an object"),
@@ -373,15 +476,39 @@ impl<'a> Value<'a> {
 
     pub fn as_f64(&self) -> f64 {
         match *self {
-            Value::Number(n) => n,
+            Value::Number(n) | Value::RawNumber(n, _) => n,
+            Value::Decimal(ref d) => d.to_f64().unwrap_or(f64::NAN),
+            _ => panic!("Not a number"),
+        }
+    }
+
+    /// The value of a number as a decimal, if it can be represented as one. Floating point
+    /// numbers are converted from their shortest representation, so that e.g. `0.1` is exactly
+    /// `0.1` rather than the nearest binary fraction.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match *self {
+            Value::Number(n) | Value::RawNumber(n, _) => decimal_from_f64(n),
+            Value::Decimal(d) => Some(d),
             _ => panic!("Not a number"),
         }
     }
 
+    /// Compare two numbers. If either is a decimal they are compared exactly as decimals,
+    /// otherwise as floating point numbers.
+    pub fn compare_numbers(&self, other: &Value) -> Option<Ordering> {
+        if self.is_decimal() || other.is_decimal() {
+            if let (Some(lhs), Some(rhs)) = (self.as_decimal(), other.as_decimal()) {
+                return Some(lhs.cmp(&rhs));
+            }
+        }
+        self.as_f64().partial_cmp(&other.as_f64())
+    }
+
     // TODO(math): Completely unchecked, audit usage
     pub fn as_usize(&self) -> usize {
         match *self {
-            Value::Number(n) => n as usize,
+            Value::Number(n) | Value::RawNumber(n, _) => n as usize,
+            Value::Decimal(ref d) => d.to_usize().unwrap_or(0),
             _ => panic!("Not a number"),
         }
     }
@@ -389,7 +516,8 @@ impl<'a> Value<'a> {
     // TODO(math): Completely unchecked, audit usage
     pub fn as_isize(&self) -> isize {
         match *self {
-            Value::Number(n) => n as isize,
+            Value::Number(n) | Value::RawNumber(n, _) => n as isize,
+            Value::Decimal(ref d) => d.to_isize().unwrap_or(0),
             _ => panic!("Not a number"),
         }
     }
@@ -515,12 +643,24 @@ impl<'a> Value<'a> {
             Self::Undefined => arena.alloc(Value::Undefined),
             Self::Null => Value::null(arena),
             Self::Number(n) => Value::number(arena, *n),
+            Self::RawNumber(n, lexeme) => arena.alloc(Value::RawNumber(*n, lexeme)),
+            Self::Decimal(d) => Value::decimal(arena, *d),
             Self::Bool(b) => arena.alloc(Value::Bool(*b)),
             Self::String(s) => arena.alloc(Value::String(s.clone())),
             Self::Array(a, f) => Value::array_from(arena, a.clone(), *f),
             Self::Object(o) => Value::object_from(o, arena),
             Self::Lambda { ast, input, frame } => Value::lambda(arena, ast, input, frame.clone()),
-            Self::NativeFn { name, arity, func } => Value::nativefn(arena, name, *arity, *func),
+            Self::NativeFn {
+                name,
+                arity,
+                func,
+                signature,
+            } => arena.alloc(Value::NativeFn {
+                name: name.clone(),
+                arity: *arity,
+                func: *func,
+                signature: *signature,
+            }),
             Self::Transformer {
                 pattern,
                 update,
@@ -540,14 +680,27 @@ impl<'a> Value<'a> {
 
     pub fn serialize(&'a self, pretty: bool) -> String {
         if pretty {
-            let serializer = Serializer::new(PrettyFormatter::default(), false);
+            let serializer =
+                Serializer::new(PrettyFormatter::default(), false).with_number_lexemes();
             serializer.serialize(self).expect("Shouldn't fail")
         } else {
-            let serializer = Serializer::new(DumpFormatter, false);
+            let serializer = Serializer::new(DumpFormatter, false).with_number_lexemes();
             serializer.serialize(self).expect("Shouldn't fail")
         }
     }
 
+    /// Convert the value to a `serde_json::Value`.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("Shouldn't fail")
+    }
+
+    /// Deserialize the value into a Rust type. If the value doesn't match the type, the error
+    /// includes the JSON path to where it didn't match.
+    pub fn deserialize_into<'v, T: serde::Deserialize<'v>>(&'v self) -> Result<T> {
+        T::deserialize(de::Deserializer::new(self))
+            .map_err(|e| Error::T1012ResultMismatch(e.path(), e.message().to_string()))
+    }
+
     // TODO: I don't have a good way to make modifications to values right now, so here's this absolutely
     // no good, very bad, shouldn't exist reference transmuter :(
     //
@@ -565,3 +718,40 @@ impl<'a> Value<'a> {
         }
     }
 }
+
+/// Convert a floating point number to a decimal using its shortest representation, which is the
+/// number that was most likely intended, e.g. `0.1` rather than `0.1000000000000000055511151231257827`.
+pub fn decimal_from_f64(n: f64) -> Option<Decimal> {
+    if !n.is_finite() {
+        return None;
+    }
+    parse_decimal(&n.to_string())
+}
+
+/// Parse a number, which may be in scientific notation, as a decimal. Numbers that can't be
+/// represented exactly, e.g. because they have too many digits, aren't rounded to fit, so that
+/// decimals are always exact.
+pub fn parse_decimal(s: &str) -> Option<Decimal> {
+    let Some((mantissa, exponent)) = s.split_once(['e', 'E']) else {
+        return Decimal::from_str_exact(s).ok();
+    };
+
+    let mantissa = Decimal::from_str_exact(mantissa).ok()?.normalize();
+    let exponent: i32 = exponent.parse().ok()?;
+    if mantissa.is_zero() {
+        return Some(Decimal::ZERO);
+    }
+
+    if exponent < 0 {
+        let scale = mantissa.scale().checked_add(exponent.unsigned_abs())?;
+        if scale > Decimal::MAX_SCALE {
+            return None;
+        }
+        let mut decimal = mantissa;
+        decimal.set_scale(scale).ok()?;
+        Some(decimal)
+    } else {
+        let power = Decimal::from_scientific(&format!("1e{}", exponent)).ok()?;
+        mantissa.checked_mul(power)
+    }
+}
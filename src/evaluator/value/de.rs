@@ -0,0 +1,383 @@
+use std::fmt;
+
+use rust_decimal::prelude::ToPrimitive;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use super::Value;
+
+/// A step in the path from the root of a value to where deserialization failed.
+#[derive(Debug)]
+enum Segment {
+    Index(usize),
+    Key(String),
+}
+
+/// An error from deserializing a value, along with where in the value it happened.
+#[derive(Debug)]
+pub struct DeserializeError {
+    /// The path to the failing value, innermost segment first, as it's built while the error is
+    /// propagated up.
+    path: Vec<Segment>,
+    message: String,
+}
+
+impl DeserializeError {
+    fn at(mut self, segment: Segment) -> Self {
+        self.path.push(segment);
+        self
+    }
+
+    /// The JSON path to where deserialization failed, e.g. `$.orders[1].total`.
+    pub fn path(&self) -> String {
+        let mut path = String::from("$");
+        for segment in self.path.iter().rev() {
+            match segment {
+                Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+                Segment::Key(key) => {
+                    let is_identifier = !key.is_empty()
+                        && !key.starts_with(|c: char| c.is_ascii_digit())
+                        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+                    if is_identifier {
+                        path.push('.');
+                        path.push_str(key);
+                    } else {
+                        path.push_str(&format!("[{}]", serde_json::Value::from(key.as_str())));
+                    }
+                }
+            }
+        }
+        path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            path: Vec::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path(), self.message)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// A deserializer that reads directly from a value, without serializing it first.
+///
+/// Values are interpreted the same way as they are serialized. In addition, because JSONata
+/// collapses sequences of a single value, a value that isn't an array can be deserialized as a
+/// sequence containing just that value, and undefined as an empty sequence.
+pub struct Deserializer<'v, 'a> {
+    value: &'v Value<'a>,
+}
+
+impl<'v, 'a> Deserializer<'v, 'a> {
+    pub fn new(value: &'v Value<'a>) -> Self {
+        Self { value }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Deserializer<'de, 'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Undefined | Value::Null => visitor.visit_unit(),
+            Value::Number(n) => visit_number(*n, visitor),
+            Value::RawNumber(n, lexeme) => {
+                if let Ok(u) = lexeme.parse::<u64>() {
+                    visitor.visit_u64(u)
+                } else if let Ok(i) = lexeme.parse::<i64>() {
+                    visitor.visit_i64(i)
+                } else {
+                    visit_number(*n, visitor)
+                }
+            }
+            Value::Decimal(ref d) => {
+                if d.is_integer() {
+                    if let Some(n) = d.to_u64() {
+                        return visitor.visit_u64(n);
+                    }
+                    if let Some(n) = d.to_i64() {
+                        return visitor.visit_i64(n);
+                    }
+                }
+                visitor.visit_f64(self.value.as_f64())
+            }
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::String(ref s) => visitor.visit_borrowed_str(s),
+            Value::Regex(ref regex) => visitor.visit_str(regex.as_pattern()),
+            Value::Array(..) | Value::Range(..) => visitor.visit_seq(Seq::new(self.value)),
+            Value::Object(..) => visitor.visit_map(Map::new(self.value)),
+            Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+                visitor.visit_borrowed_str("")
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Undefined | Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Array(..) | Value::Range(..) => visitor.visit_seq(Seq::new(self.value)),
+            Value::Undefined => visitor.visit_seq(Seq::empty()),
+            _ => visitor.visit_seq(Seq::singleton(self.value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::String(ref s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            Value::Object(ref map) if map.len() == 1 => {
+                let (variant, value) = self.value.entries().next().unwrap();
+                visitor
+                    .visit_enum(Enum { variant, value })
+                    .map_err(|e| e.at(Segment::Key(variant.to_string())))
+            }
+            _ => Err(de::Error::invalid_type(
+                unexpected(self.value),
+                &"a string or an object with a single key",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(n: f64, visitor: V) -> Result<V::Value, DeserializeError> {
+    if n.fract() == 0.0 && n >= 0.0 && n <= u64::MAX as f64 {
+        visitor.visit_u64(n as u64)
+    } else if n.fract() == 0.0 && n < 0.0 && n >= i64::MIN as f64 {
+        visitor.visit_i64(n as i64)
+    } else {
+        visitor.visit_f64(n)
+    }
+}
+
+fn unexpected<'v>(value: &'v Value<'_>) -> de::Unexpected<'v> {
+    match value {
+        Value::Undefined | Value::Null => de::Unexpected::Unit,
+        Value::Number(n) | Value::RawNumber(n, _) => de::Unexpected::Float(*n),
+        Value::Decimal(..) => de::Unexpected::Float(value.as_f64()),
+        Value::Bool(b) => de::Unexpected::Bool(*b),
+        Value::String(ref s) => de::Unexpected::Str(s),
+        Value::Regex(..) => de::Unexpected::Other("regex"),
+        Value::Array(..) | Value::Range(..) => de::Unexpected::Seq,
+        Value::Object(..) => de::Unexpected::Map,
+        Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+            de::Unexpected::Other("function")
+        }
+    }
+}
+
+/// The members of an array or range, or a single value treated as a sequence.
+struct Seq<'v, 'a> {
+    value: Option<&'v Value<'a>>,
+    singleton: bool,
+    index: usize,
+}
+
+impl<'v, 'a> Seq<'v, 'a> {
+    fn new(value: &'v Value<'a>) -> Self {
+        Self {
+            value: Some(value),
+            singleton: false,
+            index: 0,
+        }
+    }
+
+    fn singleton(value: &'v Value<'a>) -> Self {
+        Self {
+            value: Some(value),
+            singleton: true,
+            index: 0,
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            value: None,
+            singleton: false,
+            index: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.value {
+            None => 0,
+            Some(_) if self.singleton => 1,
+            Some(value) => value.len(),
+        }
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for Seq<'de, 'a> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let index = self.index;
+        if index >= self.len() {
+            return Ok(None);
+        }
+        self.index += 1;
+
+        let result = match self.value {
+            Some(value) if self.singleton => seed.deserialize(Deserializer::new(value)),
+            Some(Value::Array(ref array, _)) => seed.deserialize(Deserializer::new(array[index])),
+            Some(Value::Range(ref range)) => {
+                let n = range.start() as i64 + index as i64;
+                seed.deserialize(n.into_deserializer())
+            }
+            _ => unreachable!(),
+        };
+
+        if self.singleton {
+            result.map(Some)
+        } else {
+            result.map(Some).map_err(|e| e.at(Segment::Index(index)))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len() - self.index)
+    }
+}
+
+/// The entries of an object. Entries with undefined values are skipped, as they are when the
+/// object is serialized.
+struct Map<'v, 'a> {
+    entries: super::object::Iter<'v, 'a>,
+    current: Option<(&'v str, &'v Value<'a>)>,
+}
+
+impl<'v, 'a> Map<'v, 'a> {
+    fn new(value: &'v Value<'a>) -> Self {
+        Self {
+            entries: value.entries(),
+            current: None,
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for Map<'de, 'a> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        for (key, value) in self.entries.by_ref() {
+            if value.is_undefined() {
+                continue;
+            }
+            self.current = Some((key.as_str(), value));
+            return seed
+                .deserialize(de::value::BorrowedStrDeserializer::new(key.as_str()))
+                .map(Some);
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .current
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(Deserializer::new(value))
+            .map_err(|e| e.at(Segment::Key(key.to_string())))
+    }
+}
+
+/// An externally tagged enum variant, i.e. an object with a single key.
+struct Enum<'v, 'a> {
+    variant: &'v str,
+    value: &'v Value<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for Enum<'de, 'a> {
+    type Error = DeserializeError;
+    type Variant = Deserializer<'de, 'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::new(self.variant))?;
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Deserializer<'de, 'a> {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -12,7 +12,16 @@ impl<'a> PartialEq<Value<'a>> for Value<'a> {
         match (self, other) {
             (Value::Undefined, Value::Undefined) => true,
             (Value::Null, Value::Null) => true,
-            (Value::Number(l), Value::Number(r)) => *l == *r,
+            (
+                Value::Number(l) | Value::RawNumber(l, _),
+                Value::Number(r) | Value::RawNumber(r, _),
+            ) => *l == *r,
+            (Value::Decimal(l), Value::Decimal(r)) => *l == *r,
+            (Value::Decimal(..), _) | (_, Value::Decimal(..))
+                if self.is_number() && other.is_number() =>
+            {
+                self.as_decimal() == other.as_decimal()
+            }
             (Value::Bool(l), Value::Bool(r)) => *l == *r,
             (Value::String(l), Value::String(r)) => *l == *r,
             (Value::Array(l, ..), Value::Array(r, ..)) => *l == *r,
@@ -36,7 +45,9 @@ impl PartialEq<bool> for Value<'_> {
 impl PartialEq<usize> for Value<'_> {
     fn eq(&self, other: &usize) -> bool {
         match self {
-            Value::Number(..) => self.as_usize() == *other,
+            Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..) => {
+                self.as_usize() == *other
+            }
             _ => false,
         }
     }
@@ -45,7 +56,9 @@ impl PartialEq<usize> for Value<'_> {
 impl PartialEq<isize> for Value<'_> {
     fn eq(&self, other: &isize) -> bool {
         match self {
-            Value::Number(..) => self.as_isize() == *other,
+            Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..) => {
+                self.as_isize() == *other
+            }
             _ => false,
         }
     }
@@ -87,7 +100,8 @@ impl std::fmt::Debug for Value<'_> {
         match self {
             Self::Undefined => write!(f, "undefined"),
             Self::Null => write!(f, "null"),
-            Self::Number(n) => n.fmt(f),
+            Self::Number(n) | Self::RawNumber(n, _) => n.fmt(f),
+            Self::Decimal(d) => d.fmt(f),
             Self::Bool(b) => b.fmt(f),
             Self::String(s) => s.fmt(f),
             Self::Array(a, _) => a.fmt(f),
@@ -115,7 +129,9 @@ impl Hash for Value<'_> {
         match self {
             Value::Undefined => 0.hash(state),
             Value::Null => 1.hash(state),
-            Value::Number(n) => n.to_bits().hash(state),
+            Value::Number(n) | Value::RawNumber(n, _) => n.to_bits().hash(state),
+            // Hashed as a float, so that decimals hash the same as the numbers they are equal to
+            Value::Decimal(..) => self.as_f64().to_bits().hash(state),
             Value::Bool(b) => b.hash(state),
             Value::String(s) => s.hash(state),
             Value::Array(a, _) => a.hash(state),
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::hash::BuildHasher;
+
+use bumpalo::collections::String as BumpString;
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use hashbrown::{DefaultHashBuilder, HashTable};
+
+use super::Value;
+
+/// The number of entries above which keys are looked up with a hash table.
+const MAX_LINEAR_SEARCH: usize = 8;
+
+/// The entries of an object, which are kept in the order they were first inserted, like objects
+/// in the reference implementation. This is the order in which they are iterated, serialized and
+/// returned by functions like `$keys`.
+///
+/// Entries are stored in a vector. Small objects are searched linearly, and larger ones have a hash
+/// table of indices into the vector for looking up keys. Everything is allocated in the arena.
+pub struct ObjectMap<'a> {
+    entries: BumpVec<'a, (BumpString<'a>, &'a Value<'a>)>,
+    indices: HashTable<usize, &'a Bump>,
+    hasher: DefaultHashBuilder,
+}
+
+/// An iterator over the entries of an object, in insertion order.
+pub struct Iter<'o, 'a> {
+    entries: std::slice::Iter<'o, (BumpString<'a>, &'a Value<'a>)>,
+}
+
+impl<'o, 'a> Iterator for Iter<'o, 'a> {
+    type Item = (&'o BumpString<'a>, &'o &'a Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|(key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_, '_> {}
+
+impl<'a> ObjectMap<'a> {
+    pub fn new_in(arena: &'a Bump) -> Self {
+        Self::with_capacity_in(0, arena)
+    }
+
+    pub fn with_capacity_in(capacity: usize, arena: &'a Bump) -> Self {
+        Self {
+            entries: BumpVec::with_capacity_in(capacity, arena),
+            indices: HashTable::new_in(arena),
+            hasher: DefaultHashBuilder::default(),
+        }
+    }
+
+    pub fn allocator(&self) -> &'a Bump {
+        self.entries.bump()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn index_of(&self, key: &str) -> Option<usize> {
+        if self.indices.is_empty() {
+            return self.entries.iter().position(|(k, _)| k == key);
+        }
+
+        let hash = self.hasher.hash_one(key);
+        self.indices
+            .find(hash, |&i| self.entries[i].0 == key)
+            .copied()
+    }
+
+    fn index(&mut self, i: usize) {
+        let hash = self.hasher.hash_one(self.entries[i].0.as_str());
+        let (entries, hasher) = (&self.entries, &self.hasher);
+        self.indices
+            .insert_unique(hash, i, |&i| hasher.hash_one(entries[i].0.as_str()));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a Value<'a>> {
+        self.index_of(key).map(|i| self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    /// Insert an entry, returning the previous value for the key. A new key is added at the end,
+    /// and an existing key keeps its position.
+    pub fn insert(&mut self, key: BumpString<'a>, value: &'a Value<'a>) -> Option<&'a Value<'a>> {
+        if let Some(i) = self.index_of(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+
+        self.entries.push((key, value));
+        if !self.indices.is_empty() {
+            self.index(self.entries.len() - 1);
+        } else if self.entries.len() > MAX_LINEAR_SEARCH {
+            for i in 0..self.entries.len() {
+                self.index(i);
+            }
+        }
+        None
+    }
+
+    /// Remove an entry, keeping the order of the remaining entries.
+    pub fn remove(&mut self, key: &str) -> Option<&'a Value<'a>> {
+        if self.indices.is_empty() {
+            let index = self.index_of(key)?;
+            return Some(self.entries.remove(index).1);
+        }
+
+        let hash = self.hasher.hash_one(key);
+        let entries = &self.entries;
+        let (index, _) = self
+            .indices
+            .find_entry(hash, |&i| entries[i].0 == key)
+            .ok()?
+            .remove();
+
+        for i in self.indices.iter_mut() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        Iter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &BumpString<'a>> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &'a Value<'a>> + '_ {
+        self.entries.iter().map(|(_, value)| *value)
+    }
+}
+
+impl<'a> Extend<(BumpString<'a>, &'a Value<'a>)> for ObjectMap<'a> {
+    fn extend<I: IntoIterator<Item = (BumpString<'a>, &'a Value<'a>)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Objects are equal if they have the same entries, in any order.
+impl PartialEq for ObjectMap<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|v| *v == **value))
+    }
+}
+
+impl fmt::Debug for ObjectMap<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertion_order() {
+        let arena = Bump::new();
+
+        // Large enough to use the hash table as well as linear search
+        for size in [MAX_LINEAR_SEARCH, MAX_LINEAR_SEARCH * 4] {
+            let mut object = ObjectMap::new_in(&arena);
+            let keys: Vec<String> = (0..size).rev().map(|i| format!("k{}", i)).collect();
+            for (i, key) in keys.iter().enumerate() {
+                object.insert(
+                    BumpString::from_str_in(key, &arena),
+                    Value::number(&arena, i as f64),
+                );
+            }
+
+            // Replacing a value keeps its position, and removing one keeps the order of the rest
+            object.insert(
+                BumpString::from_str_in(&keys[0], &arena),
+                Value::null(&arena),
+            );
+            assert_eq!(object.remove(&keys[1]), Some(&*Value::number(&arena, 1)));
+            assert_eq!(object.remove(&keys[1]), None);
+
+            let mut expected = keys.clone();
+            expected.remove(1);
+            assert_eq!(
+                object.keys().map(|k| k.as_str()).collect::<Vec<_>>(),
+                expected
+            );
+            assert_eq!(object.get(&keys[0]), Some(&*Value::null(&arena)));
+            for (i, key) in keys.iter().enumerate().skip(2) {
+                assert_eq!(object.get(key), Some(&*Value::number(&arena, i as f64)));
+            }
+        }
+    }
+}
@@ -0,0 +1,654 @@
+use std::fmt;
+
+use bumpalo::Bump;
+use rust_decimal::prelude::ToPrimitive;
+use serde::ser::{
+    self, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct,
+    SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    Serializer,
+};
+
+use super::{ArrayFlags, Value};
+
+/// The largest integer that can be represented exactly by an `f64`.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Values are serialized the same way as `JSON.stringify` in the reference implementation:
+/// functions become empty strings, undefined values are skipped in objects, and undefined or
+/// non-finite numbers become `null` elsewhere.
+///
+/// Numbers with no fractional part are serialized as integers, so that they can be deserialized
+/// into integer types. Whole decimals are serialized as integers whenever they fit in one, and
+/// other decimals as the nearest `f64`.
+///
+/// Numbers that kept their original text are serialized exactly if it's an integer that fits in
+/// an `i64` or `u64`. The serde data model has no way to pass on the text of other numbers, so
+/// e.g. `1.50` is serialized as the `f64` `1.5`.
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Undefined | Value::Null => serializer.serialize_unit(),
+            Value::Number(n) => serialize_number(*n, serializer),
+            Value::RawNumber(n, lexeme) => {
+                if let Ok(i) = lexeme.parse::<i64>() {
+                    serializer.serialize_i64(i)
+                } else if let Ok(u) = lexeme.parse::<u64>() {
+                    serializer.serialize_u64(u)
+                } else {
+                    serialize_number(*n, serializer)
+                }
+            }
+            Value::Decimal(ref d) => {
+                if d.is_integer() {
+                    if let Some(n) = d.to_i64() {
+                        return serializer.serialize_i64(n);
+                    }
+                    if let Some(n) = d.to_u64() {
+                        return serializer.serialize_u64(n);
+                    }
+                }
+                serializer.serialize_f64(self.as_f64())
+            }
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::String(ref s) => serializer.serialize_str(s),
+            Value::Regex(ref regex) => serializer.serialize_str(regex.as_pattern()),
+            Value::Array(ref array, _) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for member in array.iter() {
+                    seq.serialize_element(member)?;
+                }
+                seq.end()
+            }
+            Value::Range(ref range) => {
+                let mut seq = serializer.serialize_seq(Some(range.len()))?;
+                for n in range.start()..=range.end() {
+                    seq.serialize_element(&(n as i64))?;
+                }
+                seq.end()
+            }
+            Value::Object(..) => {
+                let len = self.entries().filter(|(_, v)| !v.is_undefined()).count();
+                let mut map = serializer.serialize_map(Some(len))?;
+                for (key, value) in self.entries() {
+                    if !value.is_undefined() {
+                        map.serialize_entry(key.as_str(), value)?;
+                    }
+                }
+                map.end()
+            }
+            Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+                serializer.serialize_str("")
+            }
+        }
+    }
+}
+
+fn serialize_number<S: Serializer>(n: f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if !n.is_finite() {
+        serializer.serialize_unit()
+    } else if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER {
+        serializer.serialize_i64(n as i64)
+    } else {
+        serializer.serialize_f64(n)
+    }
+}
+
+/// An error from serializing a Rust value into a [`Value`].
+#[derive(Debug)]
+pub struct SerializeError(String);
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerializeError(msg.to_string())
+    }
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+/// A serializer that writes any serializable Rust value directly into the arena, in the same way
+/// as `serde_json::to_value`.
+pub struct ValueSerializer<'a> {
+    arena: &'a Bump,
+}
+
+impl<'a> ValueSerializer<'a> {
+    pub fn new(arena: &'a Bump) -> Self {
+        Self { arena }
+    }
+
+    /// Wrap a value in an object with a single key, for enum variants that have data.
+    fn variant(&self, variant: &str, value: &'a Value<'a>) -> &'a Value<'a> {
+        let object = Value::object_with_capacity(self.arena, 1);
+        object.insert(variant, value);
+        object
+    }
+}
+
+impl<'a> Serializer for ValueSerializer<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    type SerializeSeq = SerializeArray<'a>;
+    type SerializeTuple = SerializeArray<'a>;
+    type SerializeTupleStruct = SerializeArray<'a>;
+    type SerializeTupleVariant = SerializeArray<'a>;
+    type SerializeMap = SerializeObject<'a>;
+    type SerializeStruct = SerializeObject<'a>;
+    type SerializeStructVariant = SerializeObject<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        // Like JSON, there's no representation for NaN or infinity
+        if v.is_finite() {
+            Ok(Value::number(self.arena, v))
+        } else {
+            Ok(Value::null(self.arena))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::string(self.arena, v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::string(self.arena, v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let array = Value::array_with_capacity(self.arena, v.len(), ArrayFlags::empty());
+        for byte in v {
+            array.push(Value::number(self.arena, *byte));
+        }
+        Ok(array)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::null(self.arena))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::null(self.arena))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::null(self.arena))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::string(self.arena, variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let value = value.serialize(ValueSerializer::new(self.arena))?;
+        Ok(self.variant(variant, value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SerializeArray {
+            arena: self.arena,
+            array: Value::array_with_capacity(self.arena, len.unwrap_or(0), ArrayFlags::empty()),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let mut array = self.serialize_seq(Some(len))?;
+        array.variant = Some(variant);
+        Ok(array)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(SerializeObject {
+            arena: self.arena,
+            object: Value::object_with_capacity(self.arena, len.unwrap_or(0)),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        let mut object = self.serialize_map(Some(len))?;
+        object.variant = Some(variant);
+        Ok(object)
+    }
+}
+
+pub struct SerializeArray<'a> {
+    arena: &'a Bump,
+    array: &'a mut Value<'a>,
+    variant: Option<&'static str>,
+}
+
+impl<'a> SerializeArray<'a> {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.array
+            .push(value.serialize(ValueSerializer::new(self.arena))?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<&'a Value<'a>, SerializeError> {
+        let serializer = ValueSerializer::new(self.arena);
+        match self.variant {
+            Some(variant) => Ok(serializer.variant(variant, self.array)),
+            None => Ok(self.array),
+        }
+    }
+}
+
+impl<'a> SerializeSeq for SerializeArray<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a> SerializeTuple for SerializeArray<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a> SerializeTupleStruct for SerializeArray<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a> SerializeTupleVariant for SerializeArray<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+pub struct SerializeObject<'a> {
+    arena: &'a Bump,
+    object: &'a mut Value<'a>,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl<'a> SerializeObject<'a> {
+    fn insert<T: ?Sized + Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let value = value.serialize(ValueSerializer::new(self.arena))?;
+        self.object.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<&'a Value<'a>, SerializeError> {
+        let serializer = ValueSerializer::new(self.arena);
+        match self.variant {
+            Some(variant) => Ok(serializer.variant(variant, self.object)),
+            None => Ok(self.object),
+        }
+    }
+}
+
+impl<'a> SerializeMap for SerializeObject<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a> SerializeStruct for SerializeObject<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a> SerializeStructVariant for SerializeObject<'a> {
+    type Ok = &'a Value<'a>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// Serializes map keys, which must be strings, or something that can be written as one.
+struct KeySerializer;
+
+fn key_must_be_a_string() -> SerializeError {
+    SerializeError("map key must be a string".to_string())
+}
+
+impl Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializeError;
+
+    type SerializeSeq = Impossible<String, SerializeError>;
+    type SerializeTuple = Impossible<String, SerializeError>;
+    type SerializeTupleStruct = Impossible<String, SerializeError>;
+    type SerializeTupleVariant = Impossible<String, SerializeError>;
+    type SerializeMap = Impossible<String, SerializeError>;
+    type SerializeStruct = Impossible<String, SerializeError>;
+    type SerializeStructVariant = Impossible<String, SerializeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerializeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+}
@@ -8,8 +8,10 @@
 
 use std::io::Write;
 
+use rust_decimal::Decimal;
+
 use super::Value;
-use crate::Result;
+use crate::{Error, Result};
 
 const QU: u8 = b'"';
 const BS: u8 = b'\\';
@@ -104,6 +106,8 @@ pub struct Serializer<T: Formatter> {
     output: Vec<u8>,
     formatter: T,
     fail_on_invalid_numbers: bool,
+    number_lexemes: bool,
+    memory_limit: Option<(usize, usize)>,
 }
 
 impl<T: Formatter> Serializer<T> {
@@ -112,17 +116,45 @@ impl<T: Formatter> Serializer<T> {
             output: Vec::with_capacity(1024),
             formatter,
             fail_on_invalid_numbers,
+            number_lexemes: false,
+            memory_limit: None,
         }
     }
 
+    /// Write numbers that kept their original text exactly as they were read. This is only for
+    /// writing out results; within an expression, e.g. for `$string`, numbers are always
+    /// formatted the same way.
+    pub fn with_number_lexemes(mut self) -> Self {
+        self.number_lexemes = true;
+        self
+    }
+
+    /// Fail with a `U1002` error once the output is longer than `available` bytes, so that
+    /// serializing a large value can't go far past the memory limit of an evaluation.
+    pub fn with_memory_limit(mut self, available: usize, memory_limit: usize) -> Self {
+        self.memory_limit = Some((available, memory_limit));
+        self
+    }
+
     pub fn serialize<'a>(mut self, value: &'a Value<'a>) -> Result<String> {
         self.write_json(value)?;
+        self.check_memory()?;
 
         // SAFETY: Original strings were unicode, numbers are all ASCII,
         // therefore this is safe.
         Ok(unsafe { String::from_utf8_unchecked(self.output) })
     }
 
+    #[inline(always)]
+    fn check_memory(&self) -> Result<()> {
+        if let Some((available, memory_limit)) = self.memory_limit {
+            if self.output.len() > available {
+                return Err(Error::U1002MemoryLimit(memory_limit));
+            }
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn write(&mut self, slice: &[u8]) {
         self.output.extend_from_slice(slice);
@@ -168,6 +200,15 @@ impl<T: Formatter> Serializer<T> {
         self.write_char(b'"');
     }
 
+    /// Decimals are written exactly, without trailing zeroes in the fraction.
+    fn write_decimal(&mut self, number: &Decimal) {
+        if number.is_zero() {
+            self.write(b"0");
+        } else {
+            self.write(number.normalize().to_string().as_bytes());
+        }
+    }
+
     #[inline(always)]
     fn write_number(&mut self, number: f64) {
         const MAX_SIGNIFICANT_DIGITS: usize = 15;
@@ -274,16 +315,20 @@ impl<T: Formatter> Serializer<T> {
     }
 
     fn write_json<'a>(&mut self, value: &'a Value<'a>) -> Result<()> {
+        self.check_memory()?;
+
         match value {
             Value::Undefined => {}
             Value::Null => self.write(b"null"),
             Value::String(ref string) => self.write_string(string),
-            Value::Number(n) => {
+            Value::RawNumber(_, lexeme) if self.number_lexemes => self.write(lexeme.as_bytes()),
+            Value::Number(n) | Value::RawNumber(n, _) => {
                 if self.fail_on_invalid_numbers {
                     value.is_valid_number()?;
                 }
                 self.write_number(*n);
             }
+            Value::Decimal(ref d) => self.write_decimal(d),
             Value::Bool(true) => self.write(b"true"),
             Value::Bool(false) => self.write(b"false"),
             Value::Array(..) | Value::Range(..) => self.write_array(value)?,
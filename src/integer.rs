@@ -0,0 +1,533 @@
+//! Formatting and parsing of integers using XPath F&O 3.1 integer pictures, as used by
+//! `$formatInteger`, `$parseInteger` and the components of date/time pictures.
+//!
+//! See <https://www.w3.org/TR/xpath-functions-31/#func-format-integer>.
+
+use crate::Error;
+
+const FEW: [&str; 20] = [
+    "Zero",
+    "One",
+    "Two",
+    "Three",
+    "Four",
+    "Five",
+    "Six",
+    "Seven",
+    "Eight",
+    "Nine",
+    "Ten",
+    "Eleven",
+    "Twelve",
+    "Thirteen",
+    "Fourteen",
+    "Fifteen",
+    "Sixteen",
+    "Seventeen",
+    "Eighteen",
+    "Nineteen",
+];
+
+const ORDINALS: [&str; 20] = [
+    "Zeroth",
+    "First",
+    "Second",
+    "Third",
+    "Fourth",
+    "Fifth",
+    "Sixth",
+    "Seventh",
+    "Eighth",
+    "Ninth",
+    "Tenth",
+    "Eleventh",
+    "Twelfth",
+    "Thirteenth",
+    "Fourteenth",
+    "Fifteenth",
+    "Sixteenth",
+    "Seventeenth",
+    "Eighteenth",
+    "Nineteenth",
+];
+
+const DECADES: [&str; 9] = [
+    "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety", "Hundred",
+];
+
+const MAGNITUDES: [&str; 4] = ["Thousand", "Million", "Billion", "Trillion"];
+
+const ROMAN_NUMERALS: [(u64, &str); 13] = [
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+// The zero digits of the unicode decimal digit groups
+const DECIMAL_GROUPS: [u32; 37] = [
+    0x30, 0x0660, 0x06F0, 0x07C0, 0x0966, 0x09E6, 0x0A66, 0x0AE6, 0x0B66, 0x0BE6, 0x0C66, 0x0CE6,
+    0x0D66, 0x0DE6, 0x0E50, 0x0ED0, 0x0F20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80,
+    0x1A90, 0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0,
+    0xFF10,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Case {
+    Upper,
+    Lower,
+    Title,
+}
+
+#[derive(Debug, Clone)]
+enum Primary {
+    Letters,
+    Roman,
+    Words,
+    Decimal(DecimalPattern),
+}
+
+#[derive(Debug, Clone)]
+struct DecimalPattern {
+    zero: u32,
+    mandatory_digits: usize,
+    grouping: Grouping,
+}
+
+#[derive(Debug, Clone)]
+enum Grouping {
+    /// The same separator every `interval` digits
+    Regular { interval: usize, separator: char },
+
+    /// Separators at the given number of digits from the right, nearest first
+    Irregular(Vec<(usize, char)>),
+}
+
+/// An analysed integer picture, F&O 4.6.
+#[derive(Debug, Clone)]
+pub struct IntegerPicture {
+    primary: Primary,
+    case: Case,
+    ordinal: bool,
+}
+
+impl IntegerPicture {
+    pub fn parse(picture: &str) -> Result<IntegerPicture, Error> {
+        let (primary, modifier) = match picture.rfind(';') {
+            Some(position) => (&picture[..position], Some(&picture[position + 1..])),
+            None => (picture, None),
+        };
+
+        let ordinal = modifier.is_some_and(|modifier| modifier.starts_with('o'));
+
+        let (primary, case) = match primary {
+            "A" => (Primary::Letters, Case::Upper),
+            "a" => (Primary::Letters, Case::Lower),
+            "I" => (Primary::Roman, Case::Upper),
+            "i" => (Primary::Roman, Case::Lower),
+            "W" => (Primary::Words, Case::Upper),
+            "Ww" => (Primary::Words, Case::Title),
+            "w" => (Primary::Words, Case::Lower),
+            _ => (
+                Primary::Decimal(DecimalPattern::parse(primary)?),
+                Case::Lower,
+            ),
+        };
+
+        Ok(IntegerPicture {
+            primary,
+            case,
+            ordinal,
+        })
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        let magnitude = value.abs();
+
+        let formatted = match self.primary {
+            Primary::Letters => {
+                to_letters(magnitude, if self.case == Case::Upper { 'A' } else { 'a' })
+            }
+            Primary::Roman => {
+                let roman = to_roman(magnitude as u64);
+                if self.case == Case::Upper {
+                    roman
+                } else {
+                    roman.to_lowercase()
+                }
+            }
+            Primary::Words => {
+                let words = to_words(magnitude, self.ordinal);
+                match self.case {
+                    Case::Upper => words.to_uppercase(),
+                    Case::Lower => words.to_lowercase(),
+                    Case::Title => words,
+                }
+            }
+            Primary::Decimal(ref pattern) => pattern.format(magnitude, self.ordinal),
+        };
+
+        if value < 0.0 {
+            format!("-{}", formatted)
+        } else {
+            formatted
+        }
+    }
+
+    /// Parse a string formatted with this picture, returning `None` if it can't be parsed.
+    pub fn parse_value(&self, value: &str) -> Option<f64> {
+        match self.primary {
+            Primary::Letters => {
+                from_letters(value, if self.case == Case::Upper { 'A' } else { 'a' })
+            }
+            Primary::Roman => from_roman(&value.to_uppercase()).map(|value| value as f64),
+            Primary::Words => from_words(value),
+            Primary::Decimal(ref pattern) => pattern.parse_value(value, self.ordinal),
+        }
+    }
+}
+
+impl DecimalPattern {
+    fn parse(primary: &str) -> Result<DecimalPattern, Error> {
+        let mut zero = None;
+        let mut mandatory_digits = 0;
+        let mut separators = Vec::new();
+        let mut position = 0;
+
+        // Step through the picture from the right to find the positions of the separators
+        for ch in primary.chars().rev() {
+            let code = ch as u32;
+            if let Some(group) = DECIMAL_GROUPS
+                .iter()
+                .copied()
+                .find(|group| (*group..=group + 9).contains(&code))
+            {
+                mandatory_digits += 1;
+                position += 1;
+                match zero {
+                    None => zero = Some(group),
+                    Some(zero) if zero != group => return Err(Error::D3131MixedDecimalGroups),
+                    _ => (),
+                }
+            } else if ch == '#' {
+                position += 1;
+            } else {
+                separators.push((position, ch));
+            }
+        }
+
+        // Any other pattern is a numbering sequence, none of which are supported
+        let Some(zero) = zero else {
+            return Err(Error::D3130UnsupportedSequence(primary.to_string()));
+        };
+
+        let interval = regular_interval(&separators);
+        let grouping = if interval > 0 {
+            Grouping::Regular {
+                interval,
+                separator: separators[0].1,
+            }
+        } else {
+            Grouping::Irregular(separators)
+        };
+
+        Ok(DecimalPattern {
+            zero,
+            mandatory_digits,
+            grouping,
+        })
+    }
+
+    fn format(&self, value: f64, ordinal: bool) -> String {
+        let digits = format!("{:0>1$}", value.floor(), self.mandatory_digits);
+        let digits: Vec<char> = digits
+            .chars()
+            .map(|digit| match digit.to_digit(10) {
+                Some(value) => char::from_u32(self.zero + value).unwrap_or(digit),
+                None => digit,
+            })
+            .collect();
+
+        let mut result = String::new();
+        for (index, digit) in digits.iter().enumerate() {
+            let to_the_right = digits.len() - index;
+            let separator = match self.grouping {
+                Grouping::Regular {
+                    interval,
+                    separator,
+                } if index > 0 && to_the_right.is_multiple_of(interval) => Some(separator),
+                Grouping::Irregular(ref separators) if index > 0 => separators
+                    .iter()
+                    .find(|(position, _)| *position == to_the_right)
+                    .map(|(_, separator)| *separator),
+                _ => None,
+            };
+            result.extend(separator);
+            result.push(*digit);
+        }
+
+        if ordinal {
+            result.push_str(ordinal_suffix(&digits));
+        }
+
+        result
+    }
+
+    fn parse_value(&self, value: &str, ordinal: bool) -> Option<f64> {
+        let value = if ordinal {
+            ["st", "nd", "rd", "th"]
+                .iter()
+                .find_map(|suffix| value.strip_suffix(suffix))
+                .unwrap_or(value)
+        } else {
+            value
+        };
+
+        let is_separator = |ch: char| match self.grouping {
+            Grouping::Regular { separator, .. } => ch == separator,
+            Grouping::Irregular(ref separators) => separators.iter().any(|(_, s)| *s == ch),
+        };
+
+        let digits: String = value
+            .chars()
+            .filter(|ch| !is_separator(*ch))
+            .map(|ch| {
+                (ch as u32)
+                    .checked_sub(self.zero)
+                    .and_then(|digit| char::from_digit(digit, 10))
+                    .unwrap_or(ch)
+            })
+            .collect();
+
+        digits.parse().ok()
+    }
+}
+
+// The interval between grouping separators if they are all the same character and evenly
+// spaced, otherwise 0.
+fn regular_interval(separators: &[(usize, char)]) -> usize {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    let Some(&(_, separator)) = separators.first() else {
+        return 0;
+    };
+
+    if separators.iter().any(|(_, s)| *s != separator) {
+        return 0;
+    }
+
+    let positions: Vec<usize> = separators.iter().map(|(position, _)| *position).collect();
+    let factor = positions.iter().copied().fold(0, gcd);
+
+    if factor > 0 && (1..=positions.len()).all(|index| positions.contains(&(index * factor))) {
+        factor
+    } else {
+        0
+    }
+}
+
+fn ordinal_suffix(digits: &[char]) -> &'static str {
+    let last = digits.last().and_then(|digit| digit.to_digit(10));
+    let tens = digits
+        .len()
+        .checked_sub(2)
+        .and_then(|index| digits[index].to_digit(10));
+
+    match (tens, last) {
+        (Some(1), _) => "th",
+        (_, Some(1)) => "st",
+        (_, Some(2)) => "nd",
+        (_, Some(3)) => "rd",
+        _ => "th",
+    }
+}
+
+/// Format an integer using the given picture, e.g. `#,##0`, `w` or `I`.
+pub fn format_integer(value: f64, picture: &str) -> Result<String, Error> {
+    Ok(IntegerPicture::parse(picture)?.format(value.floor()))
+}
+
+/// Parse an integer formatted with the given picture.
+pub fn parse_integer(value: &str, picture: &str) -> Result<Option<f64>, Error> {
+    Ok(IntegerPicture::parse(picture)?.parse_value(value))
+}
+
+pub fn to_roman(mut value: u64) -> String {
+    let mut roman = String::new();
+    for (numeral_value, numeral) in ROMAN_NUMERALS {
+        while value >= numeral_value {
+            roman.push_str(numeral);
+            value -= numeral_value;
+        }
+    }
+    roman
+}
+
+pub fn from_roman(roman: &str) -> Option<u64> {
+    let mut total: i64 = 0;
+    let mut max = 1;
+
+    for ch in roman.chars().rev() {
+        let value = match ch {
+            'I' => 1,
+            'V' => 5,
+            'X' => 10,
+            'L' => 50,
+            'C' => 100,
+            'D' => 500,
+            'M' => 1000,
+            _ => return None,
+        };
+
+        if value < max {
+            total -= value;
+        } else {
+            max = value;
+            total += value;
+        }
+    }
+
+    u64::try_from(total).ok()
+}
+
+fn to_letters(mut value: f64, a: char) -> String {
+    let mut letters = Vec::new();
+    while value > 0.0 {
+        let offset = ((value - 1.0) % 26.0) as u32;
+        letters.push(char::from_u32(a as u32 + offset).unwrap_or(a));
+        value = ((value - 1.0) / 26.0).floor();
+    }
+    letters.iter().rev().collect()
+}
+
+fn from_letters(letters: &str, a: char) -> Option<f64> {
+    letters
+        .chars()
+        .rev()
+        .enumerate()
+        .try_fold(0.0, |total, (index, letter)| {
+            let value = (letter as u32).checked_sub(a as u32)?;
+            (value < 26).then(|| total + (value + 1) as f64 * 26f64.powi(index as i32))
+        })
+}
+
+/// Spell out a number in (title case) English words, e.g. `One Hundred and Twenty-Three`.
+pub fn to_words(value: f64, ordinal: bool) -> String {
+    fn lookup(num: f64, prev: bool, ordinal: bool) -> String {
+        if num <= 19.0 {
+            let words = if ordinal { ORDINALS } else { FEW };
+            format!("{}{}", if prev { " and " } else { "" }, words[num as usize])
+        } else if num < 100.0 {
+            let tens = (num / 10.0).floor() as usize;
+            let remainder = num % 10.0;
+            let mut words = format!("{}{}", if prev { " and " } else { "" }, DECADES[tens - 2]);
+            if remainder > 0.0 {
+                words.push('-');
+                words.push_str(&lookup(remainder, false, ordinal));
+            } else if ordinal {
+                words.pop();
+                words.push_str("ieth");
+            }
+            words
+        } else if num < 1000.0 {
+            let hundreds = (num / 100.0).floor() as usize;
+            let remainder = num % 100.0;
+            let mut words = format!("{}{} Hundred", if prev { ", " } else { "" }, FEW[hundreds]);
+            if remainder > 0.0 {
+                words.push_str(&lookup(remainder, true, ordinal));
+            } else if ordinal {
+                words.push_str("th");
+            }
+            words
+        } else {
+            let magnitude = ((num.log10() / 3.0).floor() as usize).min(MAGNITUDES.len());
+            let factor = 10f64.powi(magnitude as i32 * 3);
+            let mantissa = (num / factor).floor();
+            let remainder = num - mantissa * factor;
+            let mut words = format!(
+                "{}{} {}",
+                if prev { ", " } else { "" },
+                lookup(mantissa, false, false),
+                MAGNITUDES[magnitude - 1]
+            );
+            if remainder > 0.0 {
+                words.push_str(&lookup(remainder, true, ordinal));
+            } else if ordinal {
+                words.push_str("th");
+            }
+            words
+        }
+    }
+
+    lookup(value, false, ordinal)
+}
+
+fn word_value(word: &str) -> Option<f64> {
+    let find = |words: &[&str]| words.iter().position(|w| w.eq_ignore_ascii_case(word));
+
+    if let Some(value) = find(&FEW).or_else(|| find(&ORDINALS)) {
+        return Some(value as f64);
+    }
+
+    if let Some(index) = DECADES.iter().position(|decade| {
+        let decade = decade.to_lowercase();
+        word == decade || word == format!("{}ieth", &decade[..decade.len() - 1])
+    }) {
+        return Some(((index + 2) * 10) as f64);
+    }
+
+    if word == "hundredth" {
+        return Some(100.0);
+    }
+
+    MAGNITUDES
+        .iter()
+        .position(|magnitude| {
+            let magnitude = magnitude.to_lowercase();
+            word == magnitude || word == format!("{}th", magnitude)
+        })
+        .map(|index| 10f64.powi((index as i32 + 1) * 3))
+}
+
+/// Parse a number spelled out in English words, returning `None` if any of the words aren't
+/// recognised.
+pub fn from_words(text: &str) -> Option<f64> {
+    let text = text.to_lowercase();
+    let mut segments = vec![0.0];
+
+    for word in text
+        .split(|ch: char| ch.is_whitespace() || ch == '-' || ch == '\\')
+        .map(|word| word.trim_end_matches(','))
+        .filter(|word| !word.is_empty() && *word != "and")
+    {
+        let value = word_value(word)?;
+        let top = segments.pop().unwrap_or(0.0);
+        if value < 100.0 {
+            if top >= 1000.0 {
+                segments.push(top);
+                segments.push(value);
+            } else {
+                segments.push(top + value);
+            }
+        } else {
+            segments.push(top * value);
+        }
+    }
+
+    Some(segments.iter().sum())
+}
@@ -0,0 +1,437 @@
+//! A JSON parser which builds values directly in the arena, for parsing the input to an
+//! expression.
+//!
+//! Unlike the expression parser this only accepts strict JSON (RFC 8259), and it doesn't build an
+//! intermediate AST or `serde_json::Value` first.
+
+use std::borrow::Cow;
+
+use bumpalo::Bump;
+
+use crate::evaluator::value::{parse_decimal, ArrayFlags, Value};
+use crate::{Error, Result};
+
+/// The maximum depth of nested arrays and objects, so that malicious input can't overflow the
+/// stack.
+const MAX_DEPTH: usize = 128;
+
+/// How numbers in the input are represented.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// Parse numbers as exact decimals where they can be represented as one.
+    pub decimal_numbers: bool,
+
+    /// Keep the original text of numbers, so that it can be written out unchanged. Decimal
+    /// numbers are already exact and are written out in their own format, so this only applies
+    /// to numbers that are read as floats.
+    pub preserve_lexemes: bool,
+}
+
+/// Parse a JSON document into a value allocated in the arena.
+pub fn parse<'a>(arena: &'a Bump, input: &str, options: Options) -> Result<&'a Value<'a>> {
+    let mut parser = Parser {
+        arena,
+        options,
+        input,
+        bytes: input.as_bytes(),
+        position: 0,
+        depth: 0,
+    };
+
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.bytes.len() {
+        return Err(parser.error("trailing characters after JSON value"));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a, 'i> {
+    arena: &'a Bump,
+    options: Options,
+    input: &'i str,
+    bytes: &'i [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl<'a, 'i> Parser<'a, 'i> {
+    /// Create an error at the current position, which is reported as a 1-based line and column.
+    fn error(&self, message: &str) -> Error {
+        let consumed = &self.input[..self.position.min(self.input.len())];
+        let line = consumed.matches('\n').count() + 1;
+        let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+        let column = consumed[line_start..].chars().count() + 1;
+        Error::S0501InvalidJson(line, column, message.to_string())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str) -> Result<()> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<&'a Value<'a>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => {
+                let string = self.parse_string()?;
+                Ok(Value::string(self.arena, &string))
+            }
+            Some(b't') => {
+                self.expect_literal("true")?;
+                Ok(Value::bool(true))
+            }
+            Some(b'f') => {
+                self.expect_literal("false")?;
+                Ok(Value::bool(false))
+            }
+            Some(b'n') => {
+                self.expect_literal("null")?;
+                Ok(Value::null(self.arena))
+            }
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("expected value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<&'a Value<'a>> {
+        self.enter()?;
+        let array = Value::array(self.arena, ArrayFlags::empty());
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(array);
+        }
+
+        loop {
+            array.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(_) => return Err(self.error("expected `,` or `]`")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(array)
+    }
+
+    fn parse_object(&mut self) -> Result<&'a Value<'a>> {
+        self.enter()?;
+        let object = Value::object(self.arena);
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(object);
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected string key"));
+            }
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected `:`"));
+            }
+            self.position += 1;
+
+            let value = self.parse_value()?;
+            object.insert(&key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(_) => return Err(self.error("expected `,` or `}`")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(object)
+    }
+
+    /// Parse a string, borrowing it from the input if it doesn't contain any escape sequences.
+    fn parse_string(&mut self) -> Result<Cow<'i, str>> {
+        // Skip the opening quote
+        self.position += 1;
+        let start = self.position;
+
+        // Fast path for strings without escapes. The input is valid UTF-8 and the bytes we stop
+        // at are all ASCII, so the slice is always on a character boundary.
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    let string = &self.input[start..self.position];
+                    self.position += 1;
+                    return Ok(Cow::Borrowed(string));
+                }
+                Some(b'\\') => break,
+                Some(0x00..=0x1f) => return Err(self.error("control character in string")),
+                Some(_) => self.position += 1,
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+
+        let mut string = String::from(&self.input[start..self.position]);
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.position += 1;
+                    return Ok(Cow::Owned(string));
+                }
+                Some(b'\\') => {
+                    self.position += 1;
+                    let c = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{0008}',
+                        Some(b'f') => '\u{000c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.position += 1;
+                            string.push(self.parse_unicode_escape()?);
+                            continue;
+                        }
+                        Some(_) => return Err(self.error("invalid escape sequence")),
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    string.push(c);
+                    self.position += 1;
+                }
+                Some(0x00..=0x1f) => return Err(self.error("control character in string")),
+                Some(_) => {
+                    // Copy everything up to the next quote, escape or control character
+                    let run_start = self.position;
+                    while let Some(b) = self.peek() {
+                        if b == b'"' || b == b'\\' || b < 0x20 {
+                            break;
+                        }
+                        self.position += 1;
+                    }
+                    string.push_str(&self.input[run_start..self.position]);
+                }
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Parse the hex digits of a `\u` escape, and the low surrogate that must follow it if it's a
+    /// high surrogate.
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let high = self.parse_hex4()?;
+        let code_point = match high {
+            0xD800..=0xDBFF => {
+                if !self.bytes[self.position..].starts_with(b"\\u") {
+                    return Err(self.error("unpaired surrogate in string"));
+                }
+                self.position += 2;
+                let low = self.parse_hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error("unpaired surrogate in string"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(self.error("unpaired surrogate in string")),
+            _ => high,
+        };
+
+        // All surrogates have been handled, so this is always a valid character
+        char::from_u32(code_point).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let digits = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let mut value = 0;
+        for digit in digits {
+            let digit = (*digit as char)
+                .to_digit(16)
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            value = value * 16 + digit;
+        }
+        self.position += 4;
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<&'a Value<'a>> {
+        let start = self.position;
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+
+        match self.peek() {
+            Some(b'0') => self.position += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error("invalid number")),
+        }
+
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("invalid number"));
+            }
+            self.skip_digits();
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.position += 1;
+            }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("invalid number"));
+            }
+            self.skip_digits();
+        }
+
+        let lexeme = &self.input[start..self.position];
+        if self.options.decimal_numbers {
+            if let Some(decimal) = parse_decimal(lexeme) {
+                return Ok(Value::decimal(self.arena, decimal));
+            }
+        }
+
+        let number: f64 = lexeme.parse().map_err(|_| self.error("invalid number"))?;
+
+        if !number.is_finite() {
+            self.position = start;
+            return Err(self.error("number out of range"));
+        }
+
+        if self.options.preserve_lexemes {
+            return Ok(Value::raw_number(self.arena, number, lexeme));
+        }
+        Ok(Value::number(self.arena, number))
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_at(input: &str) -> (usize, usize) {
+        let arena = Bump::new();
+        match parse(&arena, input, Options::default()) {
+            Err(Error::S0501InvalidJson(line, column, _)) => (line, column),
+            result => panic!("expected invalid JSON, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parses_json() {
+        let arena = Bump::new();
+        let value = parse(
+            &arena,
+            r#" [{ "a\n\u00e9\ud83d\ude00": {} }, [1, -2.5e2, true, false, null, "b"]] "#,
+            Options::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            value.serialize(false),
+            "[{\"a\\n\u{e9}\u{1f600}\":{}},[1,-250,true,false,null,\"b\"]]"
+        );
+    }
+
+    #[test]
+    fn rejects_non_json() {
+        assert_eq!(error_at("1+1"), (1, 2));
+        assert_eq!(error_at("{'a': 1}"), (1, 2));
+        assert_eq!(error_at("[1, 2,]"), (1, 7));
+        assert_eq!(error_at("01"), (1, 2));
+        assert_eq!(error_at("\"\\ud800\""), (1, 8));
+        assert_eq!(error_at(""), (1, 1));
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        assert_eq!(error_at("{\n  \"a\": 1,\n  \"é\": tru\n}"), (3, 8));
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let input = "[".repeat(MAX_DEPTH + 1);
+        assert_eq!(error_at(&input), (1, MAX_DEPTH + 1));
+    }
+
+    #[test]
+    fn parses_decimal_numbers() {
+        let arena = Bump::new();
+        let value = parse(
+            &arena,
+            "[12345678901234567890, 1.50, -0.1, 2.5e-3, 15e-28, 0e-40, 1e40, 1e-31, 0.1234567890123456789012345678901]",
+            Options {
+                decimal_numbers: true,
+                ..Options::default()
+            },
+        )
+        .unwrap();
+
+        // Numbers that can't be represented exactly as decimals are parsed as floats rather than
+        // being rounded
+        let decimals = value.members().map(|n| n.is_decimal()).collect::<Vec<_>>();
+        assert_eq!(
+            decimals,
+            [true, true, true, true, true, true, false, false, false]
+        );
+        assert_eq!(
+            value.serialize(false),
+            "[12345678901234567890,1.5,-0.1,0.0025,0.0000000000000000000000000015,0,1e40,1e-31,0.12345678901234]"
+        );
+    }
+}
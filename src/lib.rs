@@ -1,19 +1,35 @@
 #![cfg_attr(not(doctest), doc = include_str!("../README.md"))]
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 use bumpalo::Bump;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
+mod clock;
 mod datetime;
 mod errors;
 mod evaluator;
+mod integer;
+mod json;
+mod number;
 pub mod parser;
+mod random;
+mod registry;
 
+pub use clock::{Clock, FixedClock, SystemClock};
 pub use errors::Error;
 pub use evaluator::functions::FunctionContext;
 pub use evaluator::value::{ArrayFlags, Value};
+pub use random::{RandomSource, SeededRandom, SystemRandom};
+pub use registry::{FunctionRegistry, SharedFunction};
 
-use evaluator::{frame::Frame, functions::*, Evaluator};
-use parser::ast::Ast;
+use evaluator::value::decimal_from_f64;
+use evaluator::{frame::Frame, Evaluator};
+use parser::ast::{Ast, Signature};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -21,251 +37,1733 @@ pub struct JsonAta<'a> {
     ast: Ast,
     frame: Frame<'a>,
     arena: &'a Bump,
-    names: Vec<Ast>
+    names: Vec<Ast>,
+    clock: RefCell<Option<Arc<dyn Clock>>>,
+    random: RefCell<Option<Arc<dyn RandomSource>>>,
+    registry: Cell<Option<&'a FunctionRegistry>>,
+    decimal_numbers: Cell<bool>,
+    preserve_number_lexemes: Cell<bool>,
+    memory_limit: Cell<Option<usize>>,
+    max_sequence_length: Cell<Option<usize>>,
+    max_steps: Cell<Option<usize>>,
+    steps: Cell<usize>,
 }
 
 impl<'a> JsonAta<'a> {
     pub fn new(expr: &str, arena: &'a Bump) -> Result<JsonAta<'a>> {
+        let (ast, names) = parser::parse(expr)?;
 
-        let (ast , names)  = parser::parse(expr)?;
+        Ok(Self {
+            ast,
+            frame: Frame::new(),
+            arena,
+            names,
+            clock: RefCell::new(None),
+            random: RefCell::new(None),
+            registry: Cell::new(None),
+            decimal_numbers: Cell::new(false),
+            preserve_number_lexemes: Cell::new(false),
+            memory_limit: Cell::new(None),
+            max_sequence_length: Cell::new(None),
+            max_steps: Cell::new(None),
+            steps: Cell::new(0),
+        })
+    }
+
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    pub fn names(&self) -> &Vec<Ast> {
+        &self.names
+    }
+
+    pub fn assign_var(&self, name: &str, value: &'a Value<'a>) {
+        self.frame.bind(name, value)
+    }
+
+    /// Bind a variable to any serializable Rust value, which is converted directly into the
+    /// arena, e.g. `jsonata.assign_serialized("cfg", &config)`.
+    pub fn assign_serialized<T: Serialize + ?Sized>(&self, name: &str, value: &T) -> Result<()> {
+        self.assign_var(name, Value::from_serialize(self.arena, value)?);
+        Ok(())
+    }
+
+    /// Set the clock that `$now` and `$millis` read the time from.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.borrow_mut() = Some(clock);
+    }
+
+    /// Set the source of randomness for `$random`, `$shuffle` and `$uuid`.
+    pub fn set_random_source(&self, random: Arc<dyn RandomSource>) {
+        *self.random.borrow_mut() = Some(random);
+    }
+
+    /// Seed the random number generator used by `$random`, `$shuffle` and `$uuid`, so that every
+    /// evaluation produces the same sequence of random values.
+    pub fn set_rng_seed(&self, seed: u64) {
+        self.set_random_source(Arc::new(SeededRandom(seed)));
+    }
+
+    /// Use the functions in a registry, which take precedence over the standard library. If the
+    /// registry doesn't include the standard library, its functions can't be called.
+    pub fn set_registry(&self, registry: &'a FunctionRegistry) {
+        self.registry.set(Some(registry));
+    }
+
+    /// Use exact decimal numbers rather than floating point numbers for the input, bindings and
+    /// number literals, so that e.g. `0.1 + 0.2` is exactly `0.3` and large integers keep all
+    /// their digits. Numbers that can't be represented as decimals are still floating point.
+    ///
+    /// This isn't compatible with the reference implementation, which uses JavaScript numbers.
+    pub fn set_decimal_numbers(&self, enabled: bool) {
+        self.decimal_numbers.set(enabled);
+    }
+
+    /// Keep the original text of numbers in the input, e.g. `1.50` or `12345678901234567890`, and
+    /// write it out unchanged for numbers that are passed through to the result, rather than
+    /// formatting them again. Numbers that are the result of any calculation are formatted as
+    /// usual.
+    ///
+    /// The text is only used when the result is written out with [`Value::serialize`]; within
+    /// the expression, e.g. for `$string` or `&`, numbers are formatted as usual. Serializing the
+    /// result with serde keeps integers exact, but other numbers are passed on as `f64`s.
+    ///
+    /// This only applies to JSON text input, not `serde_json::Value`s or bindings. With decimal
+    /// numbers enabled, numbers that are read as decimals are written out as decimals instead.
+    pub fn set_preserve_number_lexemes(&self, enabled: bool) {
+        self.preserve_number_lexemes.set(enabled);
+    }
+
+    /// Limit the memory allocated in the arena while evaluating, in bytes. Evaluation fails with
+    /// a `U1002` error once the limit is exceeded. Memory is allocated in chunks, so the limit is
+    /// approximate, and the input doesn't count towards it.
+    pub fn set_memory_limit(&self, bytes: usize) {
+        self.memory_limit.set(Some(bytes));
+    }
+
+    /// Limit the length of sequences and arrays produced while evaluating, e.g. by ranges or
+    /// `$map`. Evaluation fails with a `U1002` error if a longer one is produced.
+    pub fn set_max_sequence_length(&self, length: usize) {
+        self.max_sequence_length.set(Some(length));
+    }
+
+    /// Limit the number of steps an evaluation can take, where a step is evaluating part of the
+    /// expression or calling a function, and some functions take extra steps for their own work
+    /// (e.g. `$sort`). Evaluation fails with a `U1003` error if the limit is exceeded. Unlike
+    /// the time limit given to `evaluate_timeboxed`, this is deterministic, so an expression that
+    /// stays within the limit on one machine does on any other.
+    pub fn set_max_steps(&self, steps: usize) {
+        self.max_steps.set(Some(steps));
+    }
+
+    /// The number of steps taken by the last evaluation, whether or not it succeeded, e.g. to
+    /// choose a limit for [`set_max_steps`](Self::set_max_steps).
+    pub fn steps(&self) -> usize {
+        self.steps.get()
+    }
+
+    fn json_options(&self) -> json::Options {
+        json::Options {
+            decimal_numbers: self.decimal_numbers.get(),
+            preserve_lexemes: self.preserve_number_lexemes.get(),
+        }
+    }
+
+    /// Register a custom function, which can be any function or closure.
+    ///
+    /// The closure is moved into the arena, where it is never dropped, so it must be `Copy`: it
+    /// can capture references and plain values, but not owned state such as a `String` or an
+    /// `Arc`, which would be leaked. Functions that need to own their state can be registered
+    /// with a [`FunctionRegistry`] instead.
+    pub fn register_function<F>(&self, name: &str, arity: usize, implementation: F)
+    where
+        F: Fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>> + Copy + 'a,
+    {
+        let implementation = self.arena.alloc(implementation);
+        self.frame.bind(
+            name,
+            Value::closure(self.arena, name, arity, None, implementation),
+        );
+    }
+
+    /// Register a custom function whose arguments are validated against a JSONata function
+    /// signature, e.g. `<n-n?:n>`, before it is called. The arity of the function is taken from
+    /// the signature. As with [`JsonAta::register_function`], the closure must be `Copy`.
+    pub fn register_function_with_signature<F>(
+        &self,
+        name: &str,
+        signature: &str,
+        implementation: F,
+    ) -> Result<()>
+    where
+        F: Fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>> + Copy + 'a,
+    {
+        let signature = self.arena.alloc(Signature::parse(signature, 0)?);
+        let implementation = self.arena.alloc(implementation);
+        self.frame.bind(
+            name,
+            Value::closure(self.arena, name, 0, Some(signature), implementation),
+        );
+        Ok(())
+    }
+
+    pub fn evaluate(
+        &self,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<&'a Value<'a>> {
+        if let Some(bindings) = bindings {
+            for (key, json_value) in bindings.iter() {
+                let value = json_value_to_value(self.arena, json_value, self.decimal_numbers.get());
+                self.assign_var(key, value);
+            }
+        };
+
+        self.evaluate_timeboxed(input, None, None)
+    }
+
+    /// Evaluate the expression against input that has already been parsed into a
+    /// `serde_json::Value`, rather than JSON text.
+    pub fn evaluate_value(
+        &self,
+        input: &serde_json::Value,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<&'a Value<'a>> {
+        if let Some(bindings) = bindings {
+            for (key, json_value) in bindings.iter() {
+                let value = json_value_to_value(self.arena, json_value, self.decimal_numbers.get());
+                self.assign_var(key, value);
+            }
+        };
+
+        let input = json_value_to_value(self.arena, input, self.decimal_numbers.get());
+        self.evaluate_input(wrap_input(self.arena, input), None, None)
+    }
+
+    /// Evaluate the expression and deserialize the result into a Rust type.
+    pub fn evaluate_into<T: DeserializeOwned>(
+        &self,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<T> {
+        self.evaluate(input, bindings)?.deserialize_into()
+    }
+
+    pub fn evaluate_timeboxed(
+        &self,
+        input: Option<&str>,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> Result<&'a Value<'a>> {
+        self.steps.set(0);
+        let input = parse_input(self.arena, input, self.json_options())?;
+        self.evaluate_input(input, max_depth, time_limit)
+    }
+
+    fn evaluate_input(
+        &self,
+        input: &'a Value<'a>,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> Result<&'a Value<'a>> {
+        self.frame.bind("$", input);
+
+        let mut evaluator = Evaluator::new(self.arena, max_depth, time_limit)
+            .with_registry(self.registry.get())
+            .with_decimal_numbers(self.decimal_numbers.get())
+            .with_memory_limit(self.memory_limit.get())
+            .with_max_sequence_length(self.max_sequence_length.get())
+            .with_max_steps(self.max_steps.get());
+        if let Some(ref clock) = *self.clock.borrow() {
+            evaluator = evaluator.with_clock(clock.clone());
+        }
+        if let Some(ref random) = *self.random.borrow() {
+            evaluator = evaluator.with_random_source(random.as_ref());
+        }
+        let result = evaluator.evaluate(&self.ast, input, &self.frame);
+        self.steps.set(evaluator.steps());
+        result
+    }
+}
+
+/// A compiled JSONata expression.
+///
+/// Unlike [`JsonAta`], a `CompiledExpression` holds no evaluation state: each evaluation is given
+/// its own arena and bindings. This means it can be compiled once, shared between threads (e.g.
+/// in an `Arc`), and evaluated any number of times.
+#[derive(Clone)]
+pub struct CompiledExpression {
+    ast: Ast,
+    registry: Option<Arc<FunctionRegistry>>,
+    clock: Option<Arc<dyn Clock>>,
+    random: Option<Arc<dyn RandomSource>>,
+    decimal_numbers: bool,
+    preserve_number_lexemes: bool,
+    memory_limit: Option<usize>,
+    max_sequence_length: Option<usize>,
+    max_steps: Option<usize>,
+}
+
+impl CompiledExpression {
+    pub fn compile(expr: &str) -> Result<CompiledExpression> {
+        let (ast, _) = parser::parse(expr)?;
+        Ok(Self {
+            ast,
+            registry: None,
+            clock: None,
+            random: None,
+            decimal_numbers: false,
+            preserve_number_lexemes: false,
+            memory_limit: None,
+            max_sequence_length: None,
+            max_steps: None,
+        })
+    }
+
+    /// Set the clock that `$now` and `$millis` read the time from.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Set the source of randomness for `$random`, `$shuffle` and `$uuid`.
+    pub fn with_random_source(mut self, random: Arc<dyn RandomSource>) -> Self {
+        self.random = Some(random);
+        self
+    }
+
+    /// Use the functions in a registry, which take precedence over the standard library. If the
+    /// registry doesn't include the standard library, its functions can't be called.
+    pub fn with_registry(mut self, registry: Arc<FunctionRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Use exact decimal numbers rather than floating point numbers, as described in
+    /// [`JsonAta::set_decimal_numbers`].
+    pub fn with_decimal_numbers(mut self, enabled: bool) -> Self {
+        self.decimal_numbers = enabled;
+        self
+    }
+
+    /// Keep the original text of numbers in the input, as described in
+    /// [`JsonAta::set_preserve_number_lexemes`].
+    pub fn with_preserve_number_lexemes(mut self, enabled: bool) -> Self {
+        self.preserve_number_lexemes = enabled;
+        self
+    }
+
+    /// Limit the memory allocated in the arena while evaluating, as described in
+    /// [`JsonAta::set_memory_limit`].
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Limit the length of sequences and arrays produced while evaluating, as described in
+    /// [`JsonAta::set_max_sequence_length`].
+    pub fn with_max_sequence_length(mut self, length: usize) -> Self {
+        self.max_sequence_length = Some(length);
+        self
+    }
+
+    /// Limit the number of steps an evaluation can take, as described in
+    /// [`JsonAta::set_max_steps`].
+    pub fn with_max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    pub fn evaluate<'a>(
+        &'a self,
+        arena: &'a Bump,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<&'a Value<'a>> {
+        self.evaluate_timeboxed(arena, input, bindings, None, None)
+    }
+
+    /// Evaluate the expression against input that has already been parsed into a
+    /// `serde_json::Value`, rather than JSON text.
+    pub fn evaluate_value<'a>(
+        &'a self,
+        arena: &'a Bump,
+        input: &serde_json::Value,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<&'a Value<'a>> {
+        let input = json_value_to_value(arena, input, self.decimal_numbers);
+        let input = wrap_input(arena, input);
+        self.evaluate_input(arena, input, bindings, None, None).0
+    }
+
+    /// Evaluate the expression, also returning the number of steps it took whether or not it
+    /// succeeded, e.g. to choose a limit for [`with_max_steps`](Self::with_max_steps).
+    pub fn evaluate_with_steps<'a>(
+        &'a self,
+        arena: &'a Bump,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> (Result<&'a Value<'a>>, usize) {
+        match parse_input(arena, input, self.json_options()) {
+            Ok(input) => self.evaluate_input(arena, input, bindings, None, None),
+            Err(error) => (Err(error), 0),
+        }
+    }
+
+    /// Evaluate the expression and deserialize the result into a Rust type. The values produced
+    /// while evaluating are allocated in an arena that is dropped before this returns.
+    pub fn evaluate_into<T: DeserializeOwned>(
+        &self,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+    ) -> Result<T> {
+        let arena = Bump::new();
+        self.evaluate(&arena, input, bindings)?.deserialize_into()
+    }
+
+    pub fn evaluate_timeboxed<'a>(
+        &'a self,
+        arena: &'a Bump,
+        input: Option<&str>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> Result<&'a Value<'a>> {
+        let input = parse_input(arena, input, self.json_options())?;
+        self.evaluate_input(arena, input, bindings, max_depth, time_limit)
+            .0
+    }
+
+    fn json_options(&self) -> json::Options {
+        json::Options {
+            decimal_numbers: self.decimal_numbers,
+            preserve_lexemes: self.preserve_number_lexemes,
+        }
+    }
+
+    fn evaluate_input<'a>(
+        &'a self,
+        arena: &'a Bump,
+        input: &'a Value<'a>,
+        bindings: Option<&HashMap<&str, &serde_json::Value>>,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> (Result<&'a Value<'a>>, usize) {
+        let frame = Frame::new();
+        if let Some(bindings) = bindings {
+            for (key, json_value) in bindings.iter() {
+                frame.bind(
+                    key,
+                    json_value_to_value(arena, json_value, self.decimal_numbers),
+                );
+            }
+        }
+        frame.bind("$", input);
+
+        let mut evaluator = Evaluator::new(arena, max_depth, time_limit)
+            .with_registry(self.registry.as_deref())
+            .with_decimal_numbers(self.decimal_numbers)
+            .with_memory_limit(self.memory_limit)
+            .with_max_sequence_length(self.max_sequence_length)
+            .with_max_steps(self.max_steps);
+        if let Some(ref clock) = self.clock {
+            evaluator = evaluator.with_clock(clock.clone());
+        }
+        if let Some(ref random) = self.random {
+            evaluator = evaluator.with_random_source(random.as_ref());
+        }
+        let result = evaluator.evaluate(&self.ast, input, &frame);
+        (result, evaluator.steps())
+    }
+}
+
+impl fmt::Debug for CompiledExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledExpression")
+            .field("ast", &self.ast)
+            .field("registry", &self.registry)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Convert a `serde_json::Value` into a value in the arena. If `decimal_numbers` is set, integers
+/// are converted exactly and other numbers from their shortest representation.
+fn json_value_to_value<'a>(
+    arena: &'a Bump,
+    json_value: &serde_json::Value,
+    decimal_numbers: bool,
+) -> &'a mut Value<'a> {
+    match json_value {
+        serde_json::Value::Null => Value::null(arena),
+        serde_json::Value::Bool(b) => arena.alloc(Value::Bool(*b)),
+        serde_json::Value::Number(n) if decimal_numbers => {
+            let decimal = match (n.as_i64(), n.as_u64()) {
+                (Some(n), _) => Some(Decimal::from(n)),
+                (_, Some(n)) => Some(Decimal::from(n)),
+                _ => n.as_f64().and_then(decimal_from_f64),
+            };
+            match decimal {
+                Some(decimal) => Value::decimal(arena, decimal),
+                None => Value::number(arena, n.as_f64().unwrap()),
+            }
+        }
+        serde_json::Value::Number(n) => Value::number(arena, n.as_f64().unwrap()),
+        serde_json::Value::String(s) => Value::string(arena, s),
+
+        serde_json::Value::Array(a) => {
+            let array = Value::array_with_capacity(arena, a.len(), ArrayFlags::empty());
+            for v in a.iter() {
+                array.push(json_value_to_value(arena, v, decimal_numbers))
+            }
+
+            array
+        }
+        serde_json::Value::Object(o) => {
+            let object = Value::object_with_capacity(arena, o.len());
+            for (k, v) in o.iter() {
+                object.insert(k, json_value_to_value(arena, v, decimal_numbers));
+            }
+            object
+        }
+    }
+}
+
+fn parse_input<'a>(
+    arena: &'a Bump,
+    input: Option<&str>,
+    options: json::Options,
+) -> Result<&'a Value<'a>> {
+    let input = match input {
+        Some(input) => json::parse(arena, input, options)?,
+        None => Value::undefined(),
+    };
+
+    Ok(wrap_input(arena, input))
+}
+
+/// If the input is an array, wrap it in an array so that it gets treated as a single input.
+fn wrap_input<'a>(arena: &'a Bump, input: &'a Value<'a>) -> &'a Value<'a> {
+    if input.is_array() {
+        Value::wrap_in_array(arena, input, ArrayFlags::WRAPPED)
+    } else {
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Datelike, Offset, Utc};
+    use regress::Regex;
+
+    use bumpalo::collections::String as BumpString;
+
+    use super::*;
+    use crate::evaluator::value::object::ObjectMap;
+
+    #[test]
+    fn register_function_simple() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$test()", &arena).unwrap();
+        jsonata.register_function("test", 0, |ctx, _| Ok(Value::number(ctx.arena, 1)));
+
+        let result = jsonata.evaluate(Some(r#""anything""#), None);
+
+        assert_eq!(result.unwrap(), Value::number(&arena, 1));
+    }
+
+    #[test]
+    fn register_function_closure() {
+        let calls = std::cell::Cell::new(0);
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"["GB", "FR", "XX"].$country($)"#, &arena).unwrap();
+
+        let countries = HashMap::from([("GB", "United Kingdom"), ("FR", "France")]);
+        jsonata.register_function("country", 1, |ctx, args| {
+            calls.set(calls.get() + 1);
+            Ok(match countries.get(&*args[0].as_str()) {
+                Some(name) => Value::string(ctx.arena, name),
+                None => Value::undefined(),
+            })
+        });
+
+        let result = jsonata.evaluate(None, None).unwrap();
+
+        assert_eq!(result.serialize(false), r#"["United Kingdom","France"]"#);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn register_function_drops_captured_state() {
+        struct DropCounter<'c>(&'c std::cell::Cell<usize>);
+
+        impl Drop for DropCounter<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = std::cell::Cell::new(0);
+        {
+            let state = DropCounter(&drops);
+            let arena = Bump::new();
+            let jsonata = JsonAta::new("$count()", &arena).unwrap();
+            let state = &state;
+            jsonata.register_function("count", 0, move |ctx, _| {
+                Ok(Value::number(ctx.arena, state.0.get() as f64))
+            });
+
+            let result = jsonata.evaluate(None, None).unwrap();
+
+            assert_eq!(result.as_f64(), 0.0);
+        }
+
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn register_function_override_now() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$now()", &arena).unwrap();
+        jsonata.register_function("now", 0, |ctx, _| {
+            Ok(Value::string(ctx.arena, "time for tea"))
+        });
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap().as_str(), "time for tea");
+    }
+
+    #[test]
+    fn fixed_clock() {
+        let now = DateTime::parse_from_rfc3339("2024-03-01T12:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let expr = CompiledExpression::compile(
+            "[$now(), $millis(), $now('[Y0001]-[M01]-[D01]'), $toMillis('09:15', '[H]:[m]')]",
+        )
+        .unwrap()
+        .with_clock(Arc::new(FixedClock(now)));
+
+        let arena = Bump::new();
+        let result = expr.evaluate(&arena, None, None).unwrap();
+
+        assert_eq!(
+            result.serialize(false),
+            r#"["2024-03-01T12:30:00.000Z",1709296200000,"2024-03-01",1709284500000]"#
+        );
+    }
+
+    #[test]
+    fn now_is_stable_within_an_evaluation() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("[1..1000].$now() ~> $distinct() ~> $count()", &arena).unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap(), Value::number(&arena, 1));
+    }
+
+    #[test]
+    fn seeded_random_source() {
+        let expr = CompiledExpression::compile("[$random(), $shuffle([1..10]), $uuid()]")
+            .unwrap()
+            .with_random_source(Arc::new(SeededRandom(7)));
+
+        let first_arena = Bump::new();
+        let first = expr.evaluate(&first_arena, None, None).unwrap();
+        let second_arena = Bump::new();
+        let second = expr.evaluate(&second_arena, None, None).unwrap();
+
+        assert_eq!(first.serialize(false), second.serialize(false));
+        assert_eq!(first[11].as_str().len(), 36);
+    }
+
+    #[test]
+    fn register_function_map_squareroot() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$map([1,4,9,16], $squareroot)", &arena).unwrap();
+        jsonata.register_function("squareroot", 1, |ctx, args| {
+            let num = &args[0];
+            Ok(Value::number(ctx.arena, (num.as_f64()).sqrt()))
+        });
+
+        let result = jsonata.evaluate(Some(r#""anything""#), None);
+
+        assert_eq!(
+            result
+                .unwrap()
+                .members()
+                .map(|v| v.as_f64())
+                .collect::<Vec<f64>>(),
+            vec![1.0, 2.0, 3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn register_function_filter_even() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$filter([1,4,9,16], $even)", &arena).unwrap();
+        jsonata.register_function("even", 1, |_ctx, args| {
+            let num = &args[0];
+            Ok(Value::bool((num.as_f64()) % 2.0 == 0.0))
+        });
+
+        let result = jsonata.evaluate(Some(r#""anything""#), None);
+
+        assert_eq!(
+            result
+                .unwrap()
+                .members()
+                .map(|v| v.as_f64())
+                .collect::<Vec<f64>>(),
+            vec![4.0, 16.0]
+        );
+    }
+
+    #[test]
+    fn register_function_with_signature_context() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("[1, 4, 9].$squareroot()", &arena).unwrap();
+        jsonata
+            .register_function_with_signature("squareroot", "<n-:n>", |ctx, args| {
+                Ok(Value::number(ctx.arena, args[0].as_f64().sqrt()))
+            })
+            .unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(
+            result
+                .unwrap()
+                .members()
+                .map(|v| v.as_f64())
+                .collect::<Vec<f64>>(),
+            vec![1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn register_function_with_signature_invalid_argument() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"$squareroot("four")"#, &arena).unwrap();
+        jsonata
+            .register_function_with_signature("squareroot", "<n:n>", |ctx, args| {
+                Ok(Value::number(ctx.arena, args[0].as_f64().sqrt()))
+            })
+            .unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap_err().code(), "T0410");
+    }
+
+    #[test]
+    fn lambda_signature_errors_use_binding_name() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"($double := function($x)<n:n>{ $x * 2 }; $double("two"))"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "T0410 @ 41: Argument 1 of function double does not match function signature"
+        );
+    }
+
+    #[test]
+    fn builtin_signatures() {
+        let cases = [
+            (r#"$uppercase(1)"#, "T0410", 1),
+            (r#"$substring("hello", "1")"#, "T0410", 2),
+            (r#"$sum([1, "2"])"#, "T0412", 1),
+            (r#"$join(["a", 1])"#, "T0412", 1),
+            (r#"$pad("a", 2, "-", 4)"#, "T0410", 4),
+        ];
+
+        for (expr, code, arg) in cases {
+            let arena = Bump::new();
+            let jsonata = JsonAta::new(expr, &arena).unwrap();
+            let error = jsonata.evaluate(None, None).unwrap_err();
+            assert_eq!(error.code(), code, "{}", expr);
+            assert!(
+                error.to_string().contains(&format!("Argument {} of", arg)),
+                "{}: {}",
+                expr,
+                error
+            );
+        }
+
+        // The context is substituted for missing arguments, and must be the right type
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"$.$uppercase()"#, &arena).unwrap();
+        let result = jsonata.evaluate(Some(r#"["a", "b"]"#), None).unwrap();
+        assert_eq!(result.serialize(false), r#"["A","B"]"#);
+        let error = jsonata.evaluate(Some("[1]"), None).unwrap_err();
+        assert_eq!(error.code(), "T0411");
+    }
+
+    #[test]
+    fn partial_application_arity() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$substring(?, 0, ?)", &arena).unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
+
+        assert!(result.is_function());
+        assert_eq!(result.arity(), 2);
+    }
+
+    #[test]
+    fn partial_application_apply() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#""Hello World" ~> $substring(?, 0, 5)"#, &arena).unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap().as_str(), "Hello");
+    }
+
+    #[test]
+    fn format_base_large_numbers() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            "[$formatBase(1e40, 2), $formatBase(1e40, 16), $formatBase(-$power(2, 130), 16), $formatBase(1.7976931348623157e308)]",
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
+
+        assert_eq!(
+            result[0].as_str(),
+            "1110101100011001010011111000111000011010111001010010100000000000000000000000000000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(result[1].as_str(), "1d6329f1c35ca500000000000000000000");
+        assert_eq!(result[2].as_str(), "-400000000000000000000000000000000");
+        assert_eq!(result[3].as_str().len(), 309);
+        assert!(result[3].as_str().starts_with("17976931348623157081"));
+    }
+
+    #[test]
+    fn url_reserved_characters() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"[$encodeUrl("a b;/?:@&=+$,#"), $encodeUrlComponent("a b;/?:@&=+$,#"), $decodeUrl("%2F%20%3F"), $decodeUrlComponent("%2F%20%3F")]"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
+
+        assert_eq!(
+            result.serialize(false),
+            r#"["a%20b;/?:@&=+$,#","a%20b%3B%2F%3F%3A%40%26%3D%2B%24%2C%23","%2F %3F","/ ?"]"#
+        );
+    }
+
+    #[test]
+    fn url_malformed_sequence() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"$decodeUrlComponent("%C0%AF")"#, &arena).unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap_err().code(), "D3140");
+    }
+
+    #[test]
+    fn eval_sees_outer_variables() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"($x := 5; $eval("$x * 2"))"#, &arena).unwrap();
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap(), Value::number(&arena, 10));
+    }
+
+    #[test]
+    fn eval_respects_depth_limit() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"$eval("($f := function($n) { $n = 0 ? 0 : 1 + $f($n - 1) }; $f(1000))")"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate_timeboxed(None, Some(50), None);
+
+        assert_eq!(result.unwrap_err().code(), "U1001");
+    }
+
+    #[test]
+    fn memory_limit() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(r#"[1..10000000].{"a": $}"#, &arena).unwrap();
+        jsonata.set_memory_limit(1024 * 1024);
+
+        let result = jsonata.evaluate(None, None);
+        assert_eq!(result.unwrap_err(), Error::U1002MemoryLimit(1024 * 1024));
+
+        // The input doesn't count towards the limit
+        let input = format!("[{}]", vec!["1"; 100_000].join(","));
+        let expr = CompiledExpression::compile("$count($)")
+            .unwrap()
+            .with_memory_limit(64 * 1024);
+        let result = expr.evaluate(&arena, Some(&input), None).unwrap();
+        assert_eq!(result.serialize(false), "100000");
+
+        // Strings are checked before they're built
+        let limit = 1024 * 1024;
+        for expr in [
+            "$pad('x', 100000000)",
+            "$pad('x', -100000000, '#')",
+            "$string([1..2000000])",
+            "$string([1..2000000], true)",
+            "($s := $pad('x', 100000); $join([$s, $s, $s, $s, $s, $s, $s, $s, $s, $s, $s]))",
+            "($s := $pad('x', 600000); $s & $s)",
+        ] {
+            let compiled = CompiledExpression::compile(expr)
+                .unwrap()
+                .with_memory_limit(limit);
+            let arena = Bump::new();
+            let result = compiled.evaluate(&arena, None, None);
+            assert_eq!(
+                result.unwrap_err(),
+                Error::U1002MemoryLimit(limit),
+                "{}",
+                expr
+            );
+            assert!(arena.allocated_bytes() < limit * 2, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn max_sequence_length() {
+        let cases = [
+            ("[1..11]", "[1..10]"),
+            ("[1..10, 11]", "[1..9, 10]"),
+            ("$.[$, $]", "$[$ < 6].[$, $]"),
+            (
+                "$map($, function($v) { $v })",
+                "$map([1, 2], function($v) { $v })",
+            ),
+            ("$append($, 1)", "$append([1..9], 1)"),
+            (
+                "$filter($, function($v) { true })",
+                "$filter($, function($v) { $v < 5 })",
+            ),
+            (
+                "$split('a,b,c,d,e,f,g,h,i,j,k', ',')",
+                "$split('a,b,c', ',')",
+            ),
+            ("$match('abcdefghijk', /./)", "$match('abc', /./)"),
+            ("$sort($)", "$sort([3, 1, 2])"),
+            ("$reverse($)", "$reverse([1, 2])"),
+            ("$zip($, $)", "$zip([1, 2], [3, 4])"),
+        ];
+
+        let arena = Bump::new();
+        let input = "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]";
+        for (too_long, within_limit) in cases {
+            let expr = CompiledExpression::compile(too_long)
+                .unwrap()
+                .with_max_sequence_length(10);
+            let result = expr.evaluate(&arena, Some(input), None);
+            assert_eq!(
+                result.unwrap_err(),
+                Error::U1002SequenceTooLong(10),
+                "{}",
+                too_long
+            );
+
+            let expr = CompiledExpression::compile(within_limit)
+                .unwrap()
+                .with_max_sequence_length(10);
+            assert!(
+                expr.evaluate(&arena, Some(input), None).is_ok(),
+                "{}",
+                within_limit
+            );
+        }
+
+        // The limits apply to expressions evaluated with `$eval` too
+        let expr = CompiledExpression::compile(r#"$eval("[1..1000]")"#)
+            .unwrap()
+            .with_max_sequence_length(10);
+        let error = expr.evaluate(&arena, None, None).unwrap_err();
+        assert_eq!(error, Error::U1002SequenceTooLong(10));
+
+        let expr = CompiledExpression::compile(r#"$eval("[1..10000000].{'a': $}")"#)
+            .unwrap()
+            .with_memory_limit(1024 * 1024);
+        let error = expr.evaluate(&arena, None, None).unwrap_err();
+        assert_eq!(error, Error::U1002MemoryLimit(1024 * 1024));
+
+        // And to functions called by higher-order functions
+        let expr = CompiledExpression::compile("$reduce([1, 2], function($a, $b) { [1..1000] })")
+            .unwrap()
+            .with_max_sequence_length(10);
+        let error = expr.evaluate(&arena, None, None).unwrap_err();
+        assert_eq!(error, Error::U1002SequenceTooLong(10));
+
+        let expr = CompiledExpression::compile(
+            "$reduce([1, 2], function($a, $b) { [1..10000000].{'a': $} })",
+        )
+        .unwrap()
+        .with_memory_limit(1024 * 1024);
+        let error = expr.evaluate(&arena, None, None).unwrap_err();
+        assert_eq!(error, Error::U1002MemoryLimit(1024 * 1024));
+    }
+
+    #[test]
+    fn max_steps() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$sum([1..10].($ * 2))", &arena).unwrap();
+        jsonata.evaluate(None, None).unwrap();
+        let steps = jsonata.steps();
+
+        // The same evaluation always takes the same number of steps
+        jsonata.evaluate(None, None).unwrap();
+        assert_eq!(jsonata.steps(), steps);
+
+        jsonata.set_max_steps(steps);
+        assert!(jsonata.evaluate(None, None).is_ok());
+        jsonata.set_max_steps(steps - 1);
+        let error = jsonata.evaluate(None, None).unwrap_err();
+        assert_eq!(error, Error::U1003StepLimit(steps - 1));
+        assert_eq!(error.code(), "U1003");
+
+        let expr = CompiledExpression::compile("[1..100000].($ * 2)")
+            .unwrap()
+            .with_max_steps(1000);
+        let (result, steps) = expr.evaluate_with_steps(&arena, None, None);
+        assert_eq!(result.unwrap_err(), Error::U1003StepLimit(1000));
+        assert_eq!(steps, 1001);
+
+        // The limit applies to expressions evaluated with `$eval` too
+        let expr = CompiledExpression::compile(r#"$eval("[1..100000].($ * 2)")"#)
+            .unwrap()
+            .with_max_steps(1000);
+        let (result, steps) = expr.evaluate_with_steps(&arena, None, None);
+        assert_eq!(result.unwrap_err(), Error::U1003StepLimit(1000));
+        assert_eq!(steps, 1001);
+
+        // And to functions called by higher-order functions
+        let expr =
+            CompiledExpression::compile("$reduce([1..100000], function($a, $b) { $a + $b })")
+                .unwrap()
+                .with_max_steps(50000);
+        let (result, _) = expr.evaluate_with_steps(&arena, None, None);
+        assert_eq!(result.unwrap_err(), Error::U1003StepLimit(50000));
+
+        // Functions charge for their own work, e.g. sorting
+        let input = format!(
+            "[{}]",
+            (0..1024)
+                .rev()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let expr = CompiledExpression::compile("$sort($)").unwrap();
+        let (result, steps) = expr.evaluate_with_steps(&arena, Some(&input), None);
+        assert!(result.is_ok());
+        assert!(steps > 1024 * 10, "{}", steps);
+    }
+
+    #[test]
+    fn type_of_functions_and_ranges() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"[$type([1..3]), $type($sum), $type(function($x) { $x }), $type(|$|{}|)]"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
 
-        Ok(Self {
-            ast,
-            frame: Frame::new(),
-            arena,
-            names
-        })
+        assert_eq!(
+            result.serialize(false),
+            r#"["array","function","function","function"]"#
+        );
     }
 
-    pub fn ast(&self) -> &Ast {
-        &self.ast
+    #[test]
+    fn rng_seed_is_deterministic() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("[$shuffle([1..20]), $random()]", &arena).unwrap();
+        jsonata.set_rng_seed(42);
+
+        let first = jsonata.evaluate(None, None).unwrap().serialize(false);
+        let second = jsonata.evaluate(None, None).unwrap().serialize(false);
+
+        assert_eq!(first, second);
     }
 
-    pub fn names(&self) -> &Vec<Ast> {
-        &self.names
+    #[test]
+    fn evaluate_value_input() {
+        let input = serde_json::json!({ "orders": [{ "price": 10 }, { "price": 5 }] });
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$sum(orders.price)", &arena).unwrap();
+
+        let result = jsonata.evaluate_value(&input, None);
+
+        assert_eq!(result.unwrap(), Value::number(&arena, 15));
     }
 
-    pub fn assign_var(&self, name: &str, value: &'a Value<'a>) {
-        self.frame.bind(name, value)
+    #[test]
+    fn evaluate_value_array_input() {
+        let input = serde_json::json!([1, 2, 3]);
+        let expr = CompiledExpression::compile("$[1] & $count($)").unwrap();
+
+        let from_value = {
+            let arena = Bump::new();
+            let result = expr.evaluate_value(&arena, &input, None).unwrap();
+            result.serialize(false)
+        };
+        let from_text = {
+            let arena = Bump::new();
+            let result = expr.evaluate(&arena, Some("[1, 2, 3]"), None).unwrap();
+            result.serialize(false)
+        };
+
+        assert_eq!(from_value, r#""23""#);
+        assert_eq!(from_value, from_text);
     }
 
-    pub fn register_function(
-        &self,
-        name: &str,
-        arity: usize,
-        implementation: fn(FunctionContext<'a, '_>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>,
-    ) {
-        self.frame.bind(
-            name,
-            Value::nativefn(self.arena, name, arity, implementation),
+    #[test]
+    fn to_json_value() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"{ "n": 1, "f": 1.5, "s": "a", "b": true, "z": null, "u": nothing, "fn": $sum, "r": [1..3] }"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
+
+        assert_eq!(
+            result.to_json_value(),
+            serde_json::json!({
+                "n": 1, "f": 1.5, "s": "a", "b": true, "z": null, "fn": "", "r": [1, 2, 3]
+            })
         );
+        assert!(result.to_json_value()["n"].is_u64());
     }
 
-    fn json_value_to_value(&self, json_value: &serde_json::Value) -> &'a mut Value<'a> {
-        match json_value {
-            serde_json::Value::Null => Value::null(self.arena),
-            serde_json::Value::Bool(b) => self.arena.alloc(Value::Bool(*b)),
-            serde_json::Value::Number(n) => Value::number(self.arena, n.as_f64().unwrap()),
-            serde_json::Value::String(s) => Value::string(self.arena, s),
+    #[test]
+    fn serialize_into_struct() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Order {
+            name: String,
+            total: f64,
+            quantity: u32,
+        }
 
-            serde_json::Value::Array(a) => {
-                let array = Value::array_with_capacity(self.arena, a.len(), ArrayFlags::empty());
-                for v in a.iter() {
-                    array.push(self.json_value_to_value(v))
-                }
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"Order.{ "name": Product, "total": Price * Quantity, "quantity": Quantity }"#,
+            &arena,
+        )
+        .unwrap();
 
-                array
-            }
-            serde_json::Value::Object(o) => {
-                let object = Value::object_with_capacity(self.arena, o.len());
-                for (k, v) in o.iter() {
-                    object.insert(k, self.json_value_to_value(v));
+        let result = jsonata
+            .evaluate(
+                Some(
+                    r#"{ "Order": [
+                        { "Product": "Hat", "Price": 34.45, "Quantity": 2 },
+                        { "Product": "Cloak", "Price": 107.99, "Quantity": 1 }
+                    ] }"#,
+                ),
+                None,
+            )
+            .unwrap();
+
+        let orders: Vec<Order> = serde_json::from_value(result.to_json_value()).unwrap();
+        assert_eq!(
+            orders,
+            vec![
+                Order {
+                    name: "Hat".to_string(),
+                    total: 68.9,
+                    quantity: 2
+                },
+                Order {
+                    name: "Cloak".to_string(),
+                    total: 107.99,
+                    quantity: 1
                 }
-                object
-            }
-        }
+            ]
+        );
+
+        let json = serde_json::to_string(result).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            result.to_json_value()
+        );
     }
 
-    pub fn evaluate(
-        &self,
-        input: Option<&str>,
-        bindings: Option<&HashMap<&str, &serde_json::Value>>,
-    ) -> Result<&'a Value<'a>> {
-        if let Some(bindings) = bindings {
-            for (key, json_value) in bindings.iter() {
-                let value = self.json_value_to_value(json_value);
-                self.assign_var(key, value);
-            }
-        };
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Invoice {
+        customer: String,
+        // Undefined values are left out, like when they are serialized
+        #[serde(default)]
+        lines: Vec<InvoiceLine>,
+        discount: Option<f64>,
+    }
 
-        self.evaluate_timeboxed(input, None, None)
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct InvoiceLine {
+        sku: String,
+        quantity: u32,
+        status: Status,
     }
 
-    pub fn evaluate_timeboxed(
-        &self,
-        input: Option<&str>,
-        max_depth: Option<usize>,
-        time_limit: Option<usize>,
-    ) -> Result<&'a Value<'a>> {
-        let input = match input {
-            Some(input) => {
-                let input_ast = parser::parse(input)?;
-                let evaluator = Evaluator::new(None, self.arena, None, None);
-                evaluator.evaluate(&input_ast.0, Value::undefined(), &Frame::new())?
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Shipped,
+        Pending,
+    }
+
+    const INVOICE_EXPR: &str = r#"{
+        "customer": customer,
+        "lines": items.{ "sku": sku, "quantity": qty, "status": shipped ? "shipped" : "pending" },
+        "discount": discount
+    }"#;
+
+    #[test]
+    fn evaluate_into() {
+        let expr = CompiledExpression::compile(INVOICE_EXPR).unwrap();
+
+        let invoice: Invoice = expr
+            .evaluate_into(
+                Some(r#"{ "customer": "Acme", "items": [{ "sku": "A1", "qty": 3, "shipped": true }] }"#),
+                None,
+            )
+            .unwrap();
+
+        // A single line is collapsed by JSONata, but can still be read as a sequence
+        assert_eq!(
+            invoice,
+            Invoice {
+                customer: "Acme".to_string(),
+                lines: vec![InvoiceLine {
+                    sku: "A1".to_string(),
+                    quantity: 3,
+                    status: Status::Shipped
+                }],
+                discount: None
             }
-            None => Value::undefined(),
-        };
+        );
 
-        // If the input is an array, wrap it in an array so that it gets treated as a single input
-        let input = if input.is_array() {
-            Value::wrap_in_array(self.arena, input, ArrayFlags::WRAPPED)
-        } else {
-            input
-        };
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(INVOICE_EXPR, &arena).unwrap();
+        let invoice: Invoice = jsonata
+            .evaluate_into(Some(r#"{ "customer": "Acme", "discount": 0.5 }"#), None)
+            .unwrap();
+        assert_eq!(invoice.lines, vec![]);
+        assert_eq!(invoice.discount, Some(0.5));
+    }
 
-        macro_rules! bind_native {
-            ($name:literal, $arity:literal, $fn:ident) => {
-                self.frame
-                    .bind($name, Value::nativefn(&self.arena, $name, $arity, $fn));
-            };
+    #[test]
+    fn evaluate_into_mismatch() {
+        let expr = CompiledExpression::compile(INVOICE_EXPR).unwrap();
+
+        let error = expr
+            .evaluate_into::<Invoice>(
+                Some(
+                    r#"{ "customer": "Acme", "items": [
+                        { "sku": "A1", "qty": 3, "shipped": true },
+                        { "sku": "B2", "qty": 1.5, "shipped": false }
+                    ] }"#,
+                ),
+                None,
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::T1012ResultMismatch(
+                "$.lines[1].quantity".to_string(),
+                "invalid type: floating point `1.5`, expected u32".to_string()
+            )
+        );
+
+        let error = expr
+            .evaluate_into::<Invoice>(Some(r#"{ "items": [] }"#), None)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "T1012 @ $: The result doesn't match the expected type: missing field `customer`"
+        );
+    }
+
+    #[test]
+    fn assign_serialized() {
+        #[derive(serde::Serialize)]
+        struct Config {
+            currency: &'static str,
+            rates: HashMap<&'static str, f64>,
+            rounding: Option<u32>,
+            limits: (u32, u32),
+            mode: Mode,
         }
 
-        self.frame.bind("$", input);
-        bind_native!("abs", 1, fn_abs);
-        bind_native!("append", 2, fn_append);
-        bind_native!("assert", 2, fn_assert);
-        bind_native!("base64decode", 1, fn_base64_decode);
-        bind_native!("base64encode", 1, fn_base64_encode);
-        bind_native!("boolean", 1, fn_boolean);
-        bind_native!("ceil", 1, fn_ceil);
-        bind_native!("contains", 2, fn_contains);
-        bind_native!("count", 1, fn_count);
-        bind_native!("distinct", 1, fn_distinct);
-        bind_native!("each", 2, fn_each);
-        bind_native!("error", 1, fn_error);
-        bind_native!("exists", 1, fn_exists);
-        bind_native!("fromMillis", 3, from_millis);
-        bind_native!("toMillis", 2, to_millis);
-        bind_native!("single", 2, single);
-        bind_native!("filter", 2, fn_filter);
-        bind_native!("floor", 1, fn_floor);
-        bind_native!("join", 2, fn_join);
-        bind_native!("keys", 1, fn_keys);
-        bind_native!("length", 1, fn_length);
-        bind_native!("lookup", 2, fn_lookup);
-        bind_native!("lowercase", 1, fn_lowercase);
-        bind_native!("map", 2, fn_map);
-        bind_native!("match", 2, fn_match);
-        bind_native!("max", 1, fn_max);
-        bind_native!("merge", 1, fn_merge);
-        bind_native!("min", 1, fn_min);
-        bind_native!("not", 1, fn_not);
-        bind_native!("now", 2, fn_now);
-        bind_native!("number", 1, fn_number);
-        bind_native!("pad", 2, fn_pad);
-        bind_native!("power", 2, fn_power);
-        bind_native!("random", 0, fn_random);
-        bind_native!("reduce", 3, fn_reduce);
-        bind_native!("replace", 4, fn_replace);
-        bind_native!("reverse", 1, fn_reverse);
-        bind_native!("round", 2, fn_round);
-        bind_native!("sort", 2, fn_sort);
-        bind_native!("split", 3, fn_split);
-        bind_native!("sqrt", 1, fn_sqrt);
-        bind_native!("string", 1, fn_string);
-        bind_native!("substring", 3, fn_substring);
-        bind_native!("substringBefore", 2, fn_substring_before);
-        bind_native!("substringAfter", 2, fn_substring_after);
-        bind_native!("sum", 1, fn_sum);
-        bind_native!("trim", 1, fn_trim);
-        bind_native!("uppercase", 1, fn_uppercase);
-        bind_native!("zip", 1, fn_zip);
-        bind_native!("millis", 0, fn_millis);
-        bind_native!("uuid", 0, fn_uuid);
-
-        let chain_ast = Some(parser::parse(
-            "function($f, $g) { function($x){ $g($f($x)) } }",
-        )?);
-        let evaluator = Evaluator::new(Some(chain_ast.unwrap().0), self.arena, max_depth, time_limit);
-        evaluator.evaluate(&self.ast, input, &self.frame)
+        #[derive(serde::Serialize)]
+        enum Mode {
+            Strict { tolerance: f64 },
+        }
+
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"[$cfg.currency, $cfg.rates.GBP * 2, $cfg.rounding, $cfg.limits[1], $cfg.mode.Strict.tolerance]"#,
+            &arena,
+        )
+        .unwrap();
+
+        let config = Config {
+            currency: "EUR",
+            rates: HashMap::from([("GBP", 0.75)]),
+            rounding: None,
+            limits: (1, 10),
+            mode: Mode::Strict { tolerance: 0.5 },
+        };
+        jsonata.assign_serialized("cfg", &config).unwrap();
+
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), r#"["EUR",1.5,null,10,0.5]"#);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use chrono::{DateTime, Datelike, Offset, Utc};
-    use regress::Regex;
+    #[test]
+    fn assign_serialized_round_trip() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$values", &arena).unwrap();
 
-    use bumpalo::collections::String as BumpString;
+        let values = vec![(1, "one".to_string()), (2, "two".to_string())];
+        jsonata.assign_serialized("values", &values).unwrap();
 
-    use super::*;
+        let result: Vec<(u8, String)> = jsonata.evaluate_into(None, None).unwrap();
+        assert_eq!(result, vec![(1, "one".to_string()), (2, "two".to_string())]);
+    }
 
     #[test]
-    fn register_function_simple() {
+    fn assign_serialized_non_string_key() {
         let arena = Bump::new();
-        let jsonata = JsonAta::new("$test()", &arena).unwrap();
-        jsonata.register_function("test", 0, |ctx, _| Ok(Value::number(ctx.arena, 1)));
+        let jsonata = JsonAta::new("$points", &arena).unwrap();
 
-        let result = jsonata.evaluate(Some(r#"anything"#), None);
+        let points = HashMap::from([((1, 2), "a")]);
+        let error = jsonata.assign_serialized("points", &points).unwrap_err();
 
-        assert_eq!(result.unwrap(), Value::number(&arena, 1));
+        assert_eq!(
+            error,
+            Error::T1011NotSerializable("map key must be a string".to_string())
+        );
     }
 
     #[test]
-    fn register_function_override_now() {
+    fn object_key_order() {
+        let input = r#"{ "zeta": 1, "alpha": 2, "mid": { "y": 1, "x": 2 } }"#;
+        let cases = [
+            ("$", r#"{"zeta":1,"alpha":2,"mid":{"y":1,"x":2}}"#),
+            ("$keys($)", r#"["zeta","alpha","mid"]"#),
+            (
+                "$each($, function($v, $k) { $k })",
+                r#"["zeta","alpha","mid"]"#,
+            ),
+            ("$spread($).$keys()", r#"["zeta","alpha","mid"]"#),
+            (
+                r#"$merge([$, { "beta": 3, "zeta": 9 }])"#,
+                r#"{"zeta":9,"alpha":2,"mid":{"y":1,"x":2},"beta":3}"#,
+            ),
+            (
+                r#"$ ~> |$|{ "new": 1, "alpha": 5 }, ["zeta"]|"#,
+                r#"{"alpha":5,"mid":{"y":1,"x":2},"new":1}"#,
+            ),
+        ];
+
         let arena = Bump::new();
-        let jsonata = JsonAta::new("$now()", &arena).unwrap();
-        jsonata.register_function("now", 0, |ctx, _| {
-            Ok(Value::string(ctx.arena, "time for tea"))
-        });
+        for (expr, expected) in cases {
+            let compiled = CompiledExpression::compile(expr).unwrap();
+            let result = compiled.evaluate(&arena, Some(input), None).unwrap();
+            assert_eq!(result.serialize(false), expected, "{}", expr);
+        }
 
-        let result = jsonata.evaluate(None, None);
+        let expr = CompiledExpression::compile("items{ k: $sum(v) }").unwrap();
+        let result = expr
+            .evaluate(
+                &arena,
+                Some(r#"{ "items": [{ "k": "b", "v": 1 }, { "k": "a", "v": 2 }, { "k": "b", "v": 3 }] }"#),
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.serialize(false), r#"{"b":4,"a":2}"#);
+    }
 
-        assert_ne!(result.unwrap().as_str(), "time for tea");
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn object_key_order_json_value() {
+        let input = r#"{ "zeta": 1, "alpha": 2, "mid": { "y": 1, "x": 2 } }"#;
+        let arena = Bump::new();
+        let expr = CompiledExpression::compile("$").unwrap();
+        let input: serde_json::Value = serde_json::from_str(input).unwrap();
+        let result = expr.evaluate_value(&arena, &input, None).unwrap();
+        assert_eq!(
+            serde_json::to_string(&result.to_json_value()).unwrap(),
+            r#"{"zeta":1,"alpha":2,"mid":{"y":1,"x":2}}"#
+        );
     }
 
     #[test]
-    fn register_function_map_squareroot() {
+    fn decimal_numbers() {
+        let input = r#"{ "id": 12345678901234567890, "prices": [0.1, 0.2, 19.99, 1.005], "tiny": 1e-31, "huge": 1e40 }"#;
+        let cases = [
+            ("0.1 + 0.2 = 0.3", "true", "false"),
+            // Numbers that can't be exact decimals are floats rather than being rounded
+            ("1e-30 = 0", "false", "false"),
+            ("1e-30 > 0", "true", "true"),
+            ("tiny", "1e-31", "1e-31"),
+            ("huge", "1e40", "1e40"),
+            ("0.1 * 3 - 0.3 = 0", "true", "false"),
+            ("id", "12345678901234567890", "12345678901234568000"),
+            ("id + 1", "12345678901234567891", "12345678901234568000"),
+            ("$sum(prices)", "21.295", "21.2949999999999"),
+            ("$round(2.675, 2)", "2.68", "2.68"),
+            ("$round(1250, -2)", "1200", "1200"),
+            ("$abs(-id)", "12345678901234567890", "12345678901234568000"),
+            (
+                "$floor(id + 0.5)",
+                "12345678901234567890",
+                "12345678901234568000",
+            ),
+            (
+                "$ceil(id - 0.5)",
+                "12345678901234567890",
+                "12345678901234568000",
+            ),
+            ("$floor(-19.99)", "-20", "-20"),
+            ("$ceil(-19.99)", "-19", "-19"),
+            ("prices[$ > 0.2]", "[19.99,1.005]", "[19.99,1.005]"),
+            ("$max(prices) - $min(prices)", "19.89", "19.8899999999999"),
+            (
+                "$sort([0.3, 0.1 + 0.2, 0.2])",
+                "[0.2,0.3,0.3]",
+                "[0.2,0.3,0.3]",
+            ),
+            (
+                "$number(\"9007199254740993\")",
+                "9007199254740993",
+                "9007199254740992",
+            ),
+            // Results that can't be decimals fall back to floating point
+            ("1 / 0", "null", "null"),
+        ];
+
         let arena = Bump::new();
-        let jsonata = JsonAta::new("$map([1,4,9,16], $squareroot)", &arena).unwrap();
-        jsonata.register_function("squareroot", 1, |ctx, args| {
-            let num = &args[0];
-            Ok(Value::number(ctx.arena, (num.as_f64()).sqrt()))
-        });
+        for (expr, decimal, float) in cases {
+            for (enabled, expected) in [(true, decimal), (false, float)] {
+                let jsonata = JsonAta::new(expr, &arena).unwrap();
+                jsonata.set_decimal_numbers(enabled);
+                let result = jsonata.evaluate(Some(input), None).unwrap();
+                assert_eq!(
+                    result.serialize(false),
+                    expected,
+                    "{} (decimal: {})",
+                    expr,
+                    enabled
+                );
+            }
+        }
+
+        // Decimals are also used for bindings and `serde_json::Value` input
+        let expr = CompiledExpression::compile("$a * 3 + id")
+            .unwrap()
+            .with_decimal_numbers(true);
+        let a = serde_json::json!(1.1);
+        let bindings = HashMap::from([("a", &a)]);
+        let input = serde_json::json!({ "id": u64::MAX });
+        let result = expr
+            .evaluate_value(&arena, &input, Some(&bindings))
+            .unwrap();
+        assert_eq!(result.serialize(false), "18446744073709551618.3");
+    }
 
-        let result = jsonata.evaluate(Some(r#"anything"#), None);
+    #[test]
+    fn preserve_number_lexemes() {
+        let input = r#"{ "price": 1.50, "id": 12345678901234567890, "rate": 1E-7, "qty": [2.0] }"#;
+        let expr = r#"{
+            "price": price,
+            "id": id,
+            "rate": rate,
+            "qty": qty,
+            "total": price * qty[0],
+            "label": "$" & price,
+            "text": $string(qty),
+            "cheap": price = 1.5 and price < 2
+        }"#;
 
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_preserve_number_lexemes(true);
+        let result = jsonata.evaluate(Some(input), None).unwrap();
         assert_eq!(
-            result
-                .unwrap()
-                .members()
-                .map(|v| v.as_f64())
-                .collect::<Vec<f64>>(),
-            vec![1.0, 2.0, 3.0, 4.0]
+            result.serialize(false),
+            r#"{"price":1.50,"id":12345678901234567890,"rate":1E-7,"qty":[2.0],"total":3,"label":"$1.5","text":"[2]","cheap":true}"#
+        );
+
+        // Serde keeps integers exact, but not the text of other numbers
+        let json = result.to_json_value();
+        assert_eq!(json["id"].to_string(), "12345678901234567890");
+        assert_eq!(json["price"].to_string(), "1.5");
+        #[derive(serde::Deserialize)]
+        struct Ids {
+            id: u64,
+        }
+        let ids: Ids = result.deserialize_into().unwrap();
+        assert_eq!(ids.id, 12345678901234567890);
+
+        // Decimal numbers are written as decimals, but numbers that can't be keep their text
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+        jsonata.set_decimal_numbers(true);
+        jsonata.set_preserve_number_lexemes(true);
+        let result = jsonata.evaluate(Some("[1.50, 1E40]"), None).unwrap();
+        assert_eq!(result.serialize(false), "[1.5,1E40]");
+
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+        jsonata.set_preserve_number_lexemes(true);
+        let result = jsonata
+            .evaluate(Some(r#"[1.0, { "a": [0.10] }]"#), None)
+            .unwrap();
+        assert_eq!(result.serialize(false), r#"[1.0,{"a":[0.10]}]"#);
+
+        // Without preserving lexemes, numbers are formatted as usual
+        let expr = CompiledExpression::compile(expr).unwrap();
+        let result = expr.evaluate(&arena, Some(input), None).unwrap();
+        assert_eq!(
+            result.serialize(false),
+            r#"{"price":1.5,"id":12345678901234568000,"rate":1e-7,"qty":[2],"total":3,"label":"$1.5","text":"[2]","cheap":true}"#
         );
     }
 
     #[test]
-    fn register_function_filter_even() {
+    fn invalid_json_input() {
         let arena = Bump::new();
-        let jsonata = JsonAta::new("$filter([1,4,9,16], $even)", &arena).unwrap();
-        jsonata.register_function("even", 1, |_ctx, args| {
-            let num = &args[0];
-            Ok(Value::bool((num.as_f64()) % 2.0 == 0.0))
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+
+        let error = jsonata
+            .evaluate(Some("{\n  \"a\": 1,\n  \"b\": 1 + 1\n}"), None)
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "S0501 @ 3:10: Invalid JSON input: expected `,` or `}`"
+        );
+    }
+
+    #[test]
+    fn compiled_expression_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledExpression>();
+    }
+
+    #[test]
+    fn compiled_expression_evaluate_many() {
+        let expr =
+            std::sync::Arc::new(CompiledExpression::compile("$uppercase(name) & $x").unwrap());
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let expr = expr.clone();
+                std::thread::spawn(move || {
+                    let arena = Bump::new();
+                    let x = serde_json::Value::from(i);
+                    let mut bindings = HashMap::new();
+                    bindings.insert("x", &x);
+
+                    let result = expr
+                        .evaluate(&arena, Some(r#"{ "name": "world" }"#), Some(&bindings))
+                        .unwrap();
+                    result.as_str().to_string()
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results, ["WORLD0", "WORLD1", "WORLD2", "WORLD3"]);
+    }
+
+    fn domain_registry() -> FunctionRegistry {
+        let vat_rate = 0.2;
+        let mut registry = FunctionRegistry::new();
+        registry.register("vat", 1, move |ctx, args| {
+            Ok(Value::number(ctx.arena, args[0].as_f64() * vat_rate))
         });
+        registry
+            .register_with_signature("double", "<n-:n>", |ctx, args| {
+                Ok(Value::number(ctx.arena, args[0].as_f64() * 2.0))
+            })
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn registry_shared_between_expressions() {
+        let registry = Arc::new(domain_registry());
+        let vat = CompiledExpression::compile("$vat(price)")
+            .unwrap()
+            .with_registry(registry.clone());
+        let double = CompiledExpression::compile("price.$double()")
+            .unwrap()
+            .with_registry(registry);
 
-        let result = jsonata.evaluate(Some(r#"anything"#), None);
+        let arena = Bump::new();
+        let input = Some(r#"{ "price": 50 }"#);
 
         assert_eq!(
-            result
+            vat.evaluate(&arena, input, None).unwrap(),
+            Value::number(&arena, 10)
+        );
+        assert_eq!(
+            double.evaluate(&arena, input, None).unwrap(),
+            Value::number(&arena, 100)
+        );
+    }
+
+    #[test]
+    fn registry_overrides_stdlib() {
+        let mut registry = FunctionRegistry::new();
+        registry.register("uppercase", 1, |ctx, _| Ok(Value::string(ctx.arena, "shh")));
+        let expr = CompiledExpression::compile(r#"$uppercase("hello") & $lowercase("!")"#)
+            .unwrap()
+            .with_registry(Arc::new(registry));
+
+        let arena = Bump::new();
+        let result = expr.evaluate(&arena, None, None);
+
+        assert_eq!(result.unwrap().as_str(), "shh!");
+    }
+
+    #[test]
+    fn registry_without_stdlib() {
+        let mut registry = FunctionRegistry::without_stdlib();
+        registry.register("answer", 0, |ctx, _| Ok(Value::number(ctx.arena, 42)));
+        let registry = Arc::new(registry);
+
+        let arena = Bump::new();
+        let answer = CompiledExpression::compile("$answer()")
+            .unwrap()
+            .with_registry(registry.clone());
+        assert_eq!(
+            answer.evaluate(&arena, None, None).unwrap(),
+            Value::number(&arena, 42)
+        );
+
+        let sum = CompiledExpression::compile("$sum([1, 2])")
+            .unwrap()
+            .with_registry(registry);
+        assert_eq!(
+            sum.evaluate(&arena, None, None).unwrap_err().code(),
+            "T1009"
+        );
+    }
+
+    #[test]
+    fn registry_deny_builtins() {
+        let mut registry = FunctionRegistry::new();
+        registry.deny_builtins(["now", "millis", "random", "uuid", "eval"]);
+        let registry = Arc::new(registry);
+
+        let arena = Bump::new();
+        for expr in [
+            "$now()",
+            "$random()",
+            r#"$eval("1")"#,
+            r#"$eval("$millis()")"#,
+        ] {
+            let expr = CompiledExpression::compile(expr)
                 .unwrap()
-                .members()
-                .map(|v| v.as_f64())
-                .collect::<Vec<f64>>(),
-            vec![4.0, 16.0]
+                .with_registry(registry.clone());
+            assert_eq!(
+                expr.evaluate(&arena, None, None).unwrap_err().code(),
+                "T1009"
+            );
+        }
+
+        // Disabled built-ins fail however they're called
+        for expr in ["$map([1], $now)", "($f := $now; $f())"] {
+            let expr = CompiledExpression::compile(expr)
+                .unwrap()
+                .with_registry(registry.clone());
+            assert_eq!(
+                expr.evaluate(&arena, None, None).unwrap_err().code(),
+                "T1009"
+            );
+        }
+
+        // Variables can still shadow disabled built-ins
+        let expr = CompiledExpression::compile("($now := function() { 1 }; $now())")
+            .unwrap()
+            .with_registry(registry.clone());
+        assert_eq!(
+            expr.evaluate(&arena, None, None).unwrap(),
+            Value::number(&arena, 1)
+        );
+    }
+
+    #[test]
+    fn registry_allow_builtins() {
+        let mut registry = FunctionRegistry::new();
+        registry.allow_builtins(["sum", "string"]);
+
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$string($sum([1, 2])) & $uppercase('x')", &arena).unwrap();
+        jsonata.set_registry(&registry);
+
+        let err = jsonata.evaluate(None, None).unwrap_err();
+
+        assert_eq!(err.code(), "T1009");
+        assert_eq!(
+            err.to_string(),
+            "T1009 @ 24: Attempted to invoke the function $uppercase, which has been disabled"
         );
     }
 
+    #[test]
+    fn registry_with_jsonata() {
+        let registry = domain_registry();
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$vat(50) + $double(1) + $sum([1, 2])", &arena).unwrap();
+        jsonata.set_registry(&registry);
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap(), Value::number(&arena, 15));
+    }
+
+    #[test]
+    fn compiled_expression_bindings_shadow_builtins() {
+        let arena = Bump::new();
+        let expr = CompiledExpression::compile("$string & $string(1)").unwrap();
+
+        let shadow = serde_json::Value::from("one");
+        let mut bindings = HashMap::new();
+        bindings.insert("string", &shadow);
+
+        let result = expr.evaluate(&arena, None, Some(&bindings));
+
+        assert_eq!(result.unwrap_err().code(), "T1006");
+    }
+
     #[test]
     fn evaluate_with_bindings_simple() {
         let arena = Bump::new();
@@ -740,7 +2238,7 @@ mod tests {
             ArrayFlags::empty(),
         ));
 
-        let mut match_obj = hashbrown::HashMap::with_capacity_in(3, &arena);
+        let mut match_obj = ObjectMap::with_capacity_in(3, &arena);
         match_obj.insert(BumpString::from_str_in("match", &arena), match_value);
         match_obj.insert(BumpString::from_str_in("index", &arena), index_value);
         match_obj.insert(BumpString::from_str_in("groups", &arena), groups_array);
@@ -771,7 +2269,6 @@ mod tests {
     #[test]
     fn evaluate_expect_type_errors() {
         for expr in [
-            "$fromMillis('foo')",
             "$fromMillis(1, 1)",
             "$fromMillis(1, '', 1)",
             "$toMillis(1)",
@@ -783,6 +2280,13 @@ mod tests {
 
             assert_eq!(err.code(), "T0410", "Expected type error from {expr}");
         }
+
+        // As the first argument can come from the context, the string is taken to be the
+        // picture, and the context has to be a number
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$fromMillis('foo')", &arena).unwrap();
+        let err = jsonata.evaluate(Some(r#""bar""#), None).unwrap_err();
+        assert_eq!(err.code(), "T0411");
     }
 
     #[test]
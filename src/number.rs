@@ -0,0 +1,487 @@
+//! Formatting of numbers using XPath F&O 3.1 decimal format pictures, as used by `$formatNumber`.
+//!
+//! See <https://www.w3.org/TR/xpath-functions-31/#func-format-number>.
+
+use crate::evaluator::functions::multiply_by_pow10;
+use crate::Error;
+
+/// The properties of a decimal format, which can be overridden by the options object passed to
+/// `$formatNumber`.
+#[derive(Debug, Clone)]
+pub struct DecimalFormat {
+    pub decimal_separator: String,
+    pub grouping_separator: String,
+    pub exponent_separator: String,
+    pub infinity: String,
+    pub minus_sign: String,
+    pub nan: String,
+    pub percent: String,
+    pub per_mille: String,
+    pub zero_digit: char,
+    pub digit: String,
+    pub pattern_separator: String,
+}
+
+impl Default for DecimalFormat {
+    fn default() -> Self {
+        Self {
+            decimal_separator: ".".to_string(),
+            grouping_separator: ",".to_string(),
+            exponent_separator: "e".to_string(),
+            infinity: "Infinity".to_string(),
+            minus_sign: "-".to_string(),
+            nan: "NaN".to_string(),
+            percent: "%".to_string(),
+            per_mille: "\u{2030}".to_string(),
+            zero_digit: '0',
+            digit: "#".to_string(),
+            pattern_separator: ";".to_string(),
+        }
+    }
+}
+
+impl DecimalFormat {
+    /// Set a property by the name it has in the options object, unknown properties are ignored.
+    pub fn set(&mut self, property: &str, value: &str) {
+        let value = value.to_string();
+        match property {
+            "decimal-separator" => self.decimal_separator = value,
+            "grouping-separator" => self.grouping_separator = value,
+            "exponent-separator" => self.exponent_separator = value,
+            "infinity" => self.infinity = value,
+            "minus-sign" => self.minus_sign = value,
+            "NaN" => self.nan = value,
+            "percent" => self.percent = value,
+            "per-mille" => self.per_mille = value,
+            "zero-digit" => {
+                if let Some(zero_digit) = value.chars().next() {
+                    self.zero_digit = zero_digit
+                }
+            }
+            "digit" => self.digit = value,
+            "pattern-separator" => self.pattern_separator = value,
+            _ => (),
+        }
+    }
+
+    // The value of a member of the decimal digit family, i.e. the ten characters starting with
+    // the zero digit.
+    fn digit_value(&self, ch: char) -> Option<u32> {
+        (ch as u32)
+            .checked_sub(self.zero_digit as u32)
+            .filter(|value| *value < 10)
+    }
+
+    fn is_decimal_digit(&self, ch: char) -> bool {
+        self.digit_value(ch).is_some()
+    }
+
+    fn is_digit_or_optional(&self, ch: char) -> bool {
+        self.is_decimal_digit(ch) || is(&self.digit, ch)
+    }
+
+    fn is_active(&self, ch: char) -> bool {
+        self.is_decimal_digit(ch)
+            || is(&self.decimal_separator, ch)
+            || is(&self.exponent_separator, ch)
+            || is(&self.grouping_separator, ch)
+            || is(&self.digit, ch)
+            || is(&self.pattern_separator, ch)
+    }
+
+    fn to_digit(&self, digit: char) -> char {
+        match digit.to_digit(10) {
+            Some(value) => char::from_u32(self.zero_digit as u32 + value).unwrap_or(digit),
+            None => digit,
+        }
+    }
+}
+
+// Whether the (possibly multi-character) property is the given character.
+fn is(property: &str, ch: char) -> bool {
+    let mut chars = property.chars();
+    chars.next() == Some(ch) && chars.next().is_none()
+}
+
+/// Format a number using the given picture string, F&O 4.7.
+pub fn format_number(value: f64, picture: &str, format: &DecimalFormat) -> Result<String, Error> {
+    let sub_pictures: Vec<&str> = picture.split(format.pattern_separator.as_str()).collect();
+    if sub_pictures.len() > 2 {
+        return Err(Error::D3080TooManySubPictures);
+    }
+
+    let mut variables = Vec::with_capacity(2);
+    for sub_picture in sub_pictures {
+        let parts = Parts::split(sub_picture, format);
+        parts.validate(format)?;
+        variables.push(Variables::analyse(&parts, format));
+    }
+
+    // If there is no sub-picture for negative numbers, use the positive one with a minus sign
+    if variables.len() == 1 {
+        let mut negative = variables[0].clone();
+        negative.prefix = format!("{}{}", format.minus_sign, negative.prefix);
+        variables.push(negative);
+    }
+
+    let pic = if value >= 0.0 {
+        &variables[0]
+    } else {
+        &variables[1]
+    };
+
+    if value.is_nan() {
+        return Ok(format.nan.clone());
+    }
+
+    let adjusted_number = if pic.picture.contains(format.percent.as_str()) {
+        value * 100.0
+    } else if pic.picture.contains(format.per_mille.as_str()) {
+        value * 1000.0
+    } else {
+        value
+    };
+
+    if adjusted_number.is_infinite() {
+        return Ok(format!("{}{}{}", pic.prefix, format.infinity, pic.suffix));
+    }
+
+    // The sign is provided by the sub-picture, so only the magnitude is formatted
+    let mut mantissa = adjusted_number.abs();
+    let mut exponent = None;
+    if pic.minimum_exponent_size > 0 {
+        // Find the exponent such that mantissa * 10^exponent = adjusted_number
+        let max_mantissa = 10f64.powi(pic.scaling_factor as i32);
+        let min_mantissa = 10f64.powi(pic.scaling_factor as i32 - 1);
+        let mut e: i32 = 0;
+        if mantissa != 0.0 {
+            while mantissa < min_mantissa {
+                mantissa *= 10.0;
+                e -= 1;
+            }
+            while mantissa > max_mantissa {
+                mantissa /= 10.0;
+                e += 1;
+            }
+        }
+        exponent = Some(e);
+    }
+
+    let rounded = round_half_even(mantissa, pic.maximum_fractional_size)?;
+    let fixed = format!("{:.*}", pic.maximum_fractional_size, rounded);
+    let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+    let mut integer: Vec<char> = integer.trim_start_matches('0').chars().collect();
+    let mut fraction: Vec<char> = fraction.trim_end_matches('0').chars().collect();
+
+    if integer.len() < pic.minimum_integer_size {
+        let padding = pic.minimum_integer_size - integer.len();
+        integer.splice(0..0, std::iter::repeat_n('0', padding));
+    }
+    if fraction.len() < pic.minimum_fractional_size {
+        fraction.resize(pic.minimum_fractional_size, '0');
+    }
+
+    let mut result = pic.prefix.clone();
+
+    for (index, digit) in integer.iter().enumerate() {
+        let to_the_right = integer.len() - index;
+        if index > 0 && pic.is_integer_grouping_position(to_the_right) {
+            result.push_str(&format.grouping_separator);
+        }
+        result.push(format.to_digit(*digit));
+    }
+
+    if !(pic.minimum_fractional_size == 0 && fraction.is_empty()) {
+        result.push_str(&format.decimal_separator);
+    }
+
+    for (index, digit) in fraction.iter().enumerate() {
+        if index > 0 && pic.fractional_grouping_positions.contains(&index) {
+            result.push_str(&format.grouping_separator);
+        }
+        result.push(format.to_digit(*digit));
+    }
+
+    if let Some(exponent) = exponent {
+        result.push_str(&format.exponent_separator);
+        if exponent < 0 {
+            result.push_str(&format.minus_sign);
+        }
+        let digits = exponent.unsigned_abs().to_string();
+        for _ in digits.len()..pic.minimum_exponent_size {
+            result.push(format.zero_digit);
+        }
+        result.extend(digits.chars().map(|digit| format.to_digit(digit)));
+    }
+
+    result.push_str(&pic.suffix);
+
+    Ok(result)
+}
+
+// Round half to even at the given number of decimal places, as $round does.
+fn round_half_even(value: f64, precision: usize) -> Result<f64, Error> {
+    let scaled = multiply_by_pow10(value, precision as isize)?.round_ties_even();
+    multiply_by_pow10(scaled, -(precision as isize))
+}
+
+// A sub-picture split into its parts, F&O 4.7.3
+struct Parts<'p> {
+    picture: &'p str,
+    prefix: &'p str,
+    suffix: &'p str,
+    active: &'p str,
+    mantissa: &'p str,
+    exponent: Option<&'p str>,
+    integer: &'p str,
+    fractional: &'p str,
+}
+
+impl<'p> Parts<'p> {
+    fn split(picture: &'p str, format: &DecimalFormat) -> Self {
+        let is_active = |ch: char| format.is_active(ch) && !is(&format.exponent_separator, ch);
+
+        let prefix_end = picture
+            .char_indices()
+            .find(|(_, ch)| is_active(*ch))
+            .map(|(index, _)| index)
+            .unwrap_or(picture.len());
+        let suffix_start = picture
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| is_active(*ch))
+            .map(|(index, ch)| index + ch.len_utf8())
+            .unwrap_or(picture.len())
+            .max(prefix_end);
+
+        let prefix = &picture[..prefix_end];
+        let suffix = &picture[suffix_start..];
+        let active = &picture[prefix_end..suffix_start];
+
+        // The exponent separator may be the first character of the suffix, in which case the
+        // exponent is empty
+        let (mantissa, exponent) = match picture[prefix_end..].find(&format.exponent_separator) {
+            Some(position) if prefix_end + position <= suffix_start => {
+                let exponent_start =
+                    (prefix_end + position + format.exponent_separator.len()).min(suffix_start);
+                (
+                    &picture[prefix_end..prefix_end + position],
+                    Some(&picture[exponent_start..suffix_start]),
+                )
+            }
+            _ => (active, None),
+        };
+
+        let (integer, fractional) = mantissa
+            .split_once(&format.decimal_separator)
+            .unwrap_or((mantissa, ""));
+
+        Self {
+            picture,
+            prefix,
+            suffix,
+            active,
+            mantissa,
+            exponent,
+            integer,
+            fractional,
+        }
+    }
+
+    // Validate the sub-picture, F&O 4.7.3. As in the reference implementation, when more than
+    // one rule is broken the last one is reported.
+    fn validate(&self, format: &DecimalFormat) -> Result<(), Error> {
+        let mut error = None;
+        let picture = self.picture;
+        let occurrences = |property: &str| picture.matches(property).count();
+        let percent = picture.contains(format.percent.as_str());
+        let per_mille = picture.contains(format.per_mille.as_str());
+
+        if occurrences(&format.decimal_separator) > 1 {
+            error = Some(Error::D3081MultipleDecimalSeparators);
+        }
+        if occurrences(&format.percent) > 1 {
+            error = Some(Error::D3082MultiplePercents);
+        }
+        if occurrences(&format.per_mille) > 1 {
+            error = Some(Error::D3083MultiplePerMilles);
+        }
+        if percent && per_mille {
+            error = Some(Error::D3084PercentAndPerMille);
+        }
+        if !self
+            .mantissa
+            .chars()
+            .any(|ch| format.is_digit_or_optional(ch))
+        {
+            error = Some(Error::D3085NoMantissaDigits);
+        }
+        if self.active.chars().any(|ch| !format.is_active(ch)) {
+            error = Some(Error::D3086PassiveCharacter);
+        }
+        match picture.find(&format.decimal_separator) {
+            Some(position) => {
+                let before = &picture[..position];
+                let after = &picture[position + format.decimal_separator.len()..];
+                if before.ends_with(&format.grouping_separator)
+                    || after.starts_with(&format.grouping_separator)
+                {
+                    error = Some(Error::D3087GroupingAdjacentToDecimal);
+                }
+            }
+            None => {
+                if self.integer.ends_with(&format.grouping_separator) {
+                    error = Some(Error::D3088GroupingAtEndOfInteger);
+                }
+            }
+        }
+        if picture.contains(&format.grouping_separator.repeat(2)) {
+            error = Some(Error::D3089AdjacentGroupingSeparators);
+        }
+        if let Some(position) = self.integer.find(&format.digit) {
+            if self.integer[..position]
+                .chars()
+                .any(|ch| format.is_decimal_digit(ch))
+            {
+                error = Some(Error::D3090DigitBeforeOptionalDigit);
+            }
+        }
+        if let Some(position) = self.fractional.rfind(&format.digit) {
+            if self.fractional[position..]
+                .chars()
+                .any(|ch| format.is_decimal_digit(ch))
+            {
+                error = Some(Error::D3091OptionalDigitBeforeDigit);
+            }
+        }
+        if let Some(exponent) = self.exponent {
+            if !exponent.is_empty() && (percent || per_mille) {
+                error = Some(Error::D3092PercentWithExponent);
+            }
+            if exponent.is_empty() || exponent.chars().any(|ch| !format.is_decimal_digit(ch)) {
+                error = Some(Error::D3093InvalidExponent);
+            }
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+// The variables derived from analysing a sub-picture, F&O 4.7.4
+#[derive(Debug, Clone)]
+struct Variables<'p> {
+    picture: &'p str,
+    prefix: String,
+    suffix: String,
+    integer_grouping_positions: Vec<usize>,
+    regular_grouping: usize,
+    fractional_grouping_positions: Vec<usize>,
+    minimum_integer_size: usize,
+    scaling_factor: usize,
+    minimum_fractional_size: usize,
+    maximum_fractional_size: usize,
+    minimum_exponent_size: usize,
+}
+
+impl<'p> Variables<'p> {
+    fn analyse(parts: &Parts<'p>, format: &DecimalFormat) -> Self {
+        let count = |part: &str, include_optional: bool| {
+            part.chars()
+                .filter(|ch| {
+                    format.is_decimal_digit(*ch) || (include_optional && is(&format.digit, *ch))
+                })
+                .count()
+        };
+
+        // The number of digits to the right of each grouping separator in the integer part, and
+        // to the left of each one in the fractional part
+        let integer_grouping_positions: Vec<usize> = parts
+            .integer
+            .match_indices(&format.grouping_separator)
+            .map(|(index, _)| count(&parts.integer[index..], true))
+            .collect();
+        let fractional_grouping_positions = parts
+            .fractional
+            .match_indices(&format.grouping_separator)
+            .map(|(index, _)| count(&parts.fractional[..index], true))
+            .collect();
+
+        let mut minimum_integer_size = count(parts.integer, false);
+        let scaling_factor = minimum_integer_size;
+        let mut minimum_fractional_size = count(parts.fractional, false);
+        let mut maximum_fractional_size = count(parts.fractional, true);
+
+        if minimum_integer_size == 0 && maximum_fractional_size == 0 {
+            if parts.exponent.is_some() {
+                minimum_fractional_size = 1;
+                maximum_fractional_size = 1;
+            } else {
+                minimum_integer_size = 1;
+            }
+        }
+        if parts.exponent.is_some()
+            && minimum_integer_size == 0
+            && parts.integer.contains(&format.digit)
+        {
+            minimum_integer_size = 1;
+        }
+        if minimum_integer_size == 0 && minimum_fractional_size == 0 {
+            minimum_fractional_size = 1;
+        }
+
+        let minimum_exponent_size = parts
+            .exponent
+            .map(|exponent| count(exponent, false))
+            .unwrap_or(0);
+
+        Self {
+            picture: parts.picture,
+            prefix: parts.prefix.to_string(),
+            suffix: parts.suffix.to_string(),
+            regular_grouping: regular_grouping(&integer_grouping_positions),
+            integer_grouping_positions,
+            fractional_grouping_positions,
+            minimum_integer_size,
+            scaling_factor,
+            minimum_fractional_size,
+            maximum_fractional_size,
+            minimum_exponent_size,
+        }
+    }
+
+    // Whether a grouping separator goes before the digit with the given number of digits to its
+    // right (including itself) in the integer part.
+    fn is_integer_grouping_position(&self, to_the_right: usize) -> bool {
+        if self.regular_grouping > 0 {
+            to_the_right.is_multiple_of(self.regular_grouping)
+        } else {
+            self.integer_grouping_positions.contains(&to_the_right)
+        }
+    }
+}
+
+// If the grouping positions are all multiples of the first, i.e. there is the same interval
+// between each of them, then that interval, otherwise 0.
+fn regular_grouping(positions: &[usize]) -> usize {
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    let Some(factor) = positions.iter().copied().reduce(gcd) else {
+        return 0;
+    };
+
+    if factor > 0 && (1..=positions.len()).all(|index| positions.contains(&(index * factor))) {
+        factor
+    } else {
+        0
+    }
+}
@@ -1,6 +1,7 @@
 pub mod ast;
 pub mod expressions;
 mod process;
+pub mod signature;
 mod symbol;
 mod tokenizer;
 
@@ -14,7 +15,7 @@ use tokenizer::*;
 pub struct Parser<'a> {
     pub tokenizer: Tokenizer<'a>,
     pub token: Token,
-    names: Vec<Ast>
+    names: Vec<Ast>,
 }
 
 impl<'a> Parser<'a> {
@@ -23,7 +24,7 @@ impl<'a> Parser<'a> {
         Ok(Self {
             token: tokenizer.next_token()?,
             tokenizer,
-            names: vec![]
+            names: vec![],
         })
     }
 
@@ -32,7 +33,7 @@ impl<'a> Parser<'a> {
     }
 
     pub fn names(&self) -> &Vec<Ast> {
-      &self.names
+        &self.names
     }
 
     pub fn next_token(&mut self) -> Result<()> {
@@ -68,7 +69,7 @@ impl<'a> Parser<'a> {
         let mut left = last.null_denotation(self)?;
 
         if let AstKind::Name(_) = left.kind {
-          self.names.push(left.clone());
+            self.names.push(left.clone());
         }
 
         while bp < self.token.left_binding_power() {
@@ -77,9 +78,8 @@ impl<'a> Parser<'a> {
             left = last.left_denotation(self, left)?;
 
             if let AstKind::Name(_) = left.kind {
-              self.names.push(left.clone());
+                self.names.push(left.clone());
             }
-
         }
 
         Ok(left)
@@ -211,6 +211,14 @@ mod tests {
         )
     "# ; "function definition 4"
     )]
+    #[test_case(
+        r#"
+        (
+          $sum := function($a, $b)<a<n>n?:n> { $a + $b };
+          $sum([1, 2], 3)
+        )
+    "# ; "function signature"
+    )]
     #[test_case(
         r#"
         (
@@ -223,15 +231,15 @@ mod tests {
     #[test_case(
         "Customer.Email ~> $substringAfter(\"@\") ~> $substringBefore(\".\") ~> $uppercase()" ; "function application"
     )]
-    // #[test_case(
-    //     r#"
-    //     Account.Order.Product.{
-    //       'Product': `Product Name`,
-    //       'Order': %.OrderID,
-    //       'Account': %.%.`Account Name`
-    //     }
-    // "# ; "parent operator"
-    // )]
+    #[test_case(
+        r#"
+        Account.Order.Product.{
+          'Product': `Product Name`,
+          'Order': %.OrderID,
+          'Account': %.%.`Account Name`
+        }
+    "# ; "parent operator"
+    )]
     // #[test_case(
     //     r#"
     //     library.books#$i['Kernighan' in authors].{
@@ -1,5 +1,6 @@
 // Re-export for use in evaluator.
 pub use super::expressions::RegexLiteral;
+pub use super::signature::Signature;
 
 // Object constructor, represented by tuples of (key, value)
 pub type Object = Vec<(Ast, Ast)>;
@@ -7,6 +8,27 @@ pub type Object = Vec<(Ast, Ast)>;
 // Sort terms, representend by expresions and a bool indicating descending/ascending
 pub type SortTerms = Vec<(Ast, bool)>;
 
+/// The slot of a parent operator. During AST processing, the slot is passed back up the tree
+/// until the step that the parent refers to is found, at which point that step binds its input
+/// to the slot's label in the tuple stream.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    /// The label that the ancestor is bound to in the tuple stream
+    pub label: String,
+
+    /// The number of steps that still need to be walked back to find the ancestor
+    pub level: usize,
+}
+
+impl Slot {
+    pub fn new(char_index: usize) -> Self {
+        Self {
+            label: format!("!{}", char_index),
+            level: 1,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum UnaryOp {
     Minus(Box<Ast>),
@@ -86,7 +108,7 @@ pub enum AstKind {
     Block(Vec<Ast>),
     Wildcard,
     Descendent,
-    Parent,
+    Parent(Slot),
     Function {
         name: String,
         proc: Box<Ast>,
@@ -99,6 +121,7 @@ pub enum AstKind {
         args: Vec<Ast>,
         body: Box<Ast>,
         thunk: bool,
+        signature: Option<Signature>,
     },
     Ternary {
         cond: Box<Ast>,
@@ -148,6 +171,13 @@ pub struct Ast {
 
     // A variable to bind the context of a step to
     pub focus: Option<String>,
+
+    /// The labels of parent operators that resolved to this step, which are bound to the input
+    /// of the step in the tuple stream.
+    pub ancestors: Vec<String>,
+
+    /// Parent operator slots within this expression that have not yet been resolved to a step.
+    pub seeking_parent: Vec<Slot>,
 }
 
 impl Default for Ast {
@@ -170,6 +200,8 @@ impl Ast {
             tuple: false,
             index: None,
             focus: None,
+            ancestors: Vec::new(),
+            seeking_parent: Vec::new(),
         }
     }
 }
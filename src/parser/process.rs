@@ -5,7 +5,14 @@ use super::*;
 
 impl Ast {
     pub fn process(self) -> Result<Ast> {
-        process_ast(self)
+        let ast = process_ast(self)?;
+
+        // Any parent that is still unresolved at the top level can't be derived
+        if matches!(ast.kind, AstKind::Parent(..)) || !ast.seeking_parent.is_empty() {
+            return Err(Error::S0217ParentNotDerivable(ast.char_index));
+        }
+
+        Ok(ast)
     }
 }
 
@@ -25,7 +32,7 @@ pub fn process_ast(node: Ast) -> Result<Ast> {
             ref mut args,
             ..
         } => {
-            process_function(proc, args)?;
+            process_function(proc, args, &mut node.seeking_parent)?;
             node
         }
         AstKind::Lambda { ref mut body, .. } => {
@@ -34,7 +41,6 @@ pub fn process_ast(node: Ast) -> Result<Ast> {
         }
         AstKind::Ternary { .. } => process_ternary(node)?,
         AstKind::Transform { .. } => process_transform(node)?,
-        AstKind::Parent => unimplemented!("Parent not yet implemented - Marin"),
         _ => node,
     };
 
@@ -60,6 +66,7 @@ fn process_block(node: Ast) -> Result<Ast> {
     if let AstKind::Block(ref mut exprs) = node.kind {
         for expr in exprs {
             *expr = process_ast(take(expr))?;
+            push_ancestry(&mut node.seeking_parent, expr);
         }
     }
     Ok(node)
@@ -73,10 +80,13 @@ fn process_ternary(node: Ast) -> Result<Ast> {
         ref mut falsy,
     } = node.kind
     {
-        *cond = Box::new(process_ast(take(cond))?);
-        *truthy = Box::new(process_ast(take(truthy))?);
+        **cond = process_ast(take(cond))?;
+        push_ancestry(&mut node.seeking_parent, cond);
+        **truthy = process_ast(take(truthy))?;
+        push_ancestry(&mut node.seeking_parent, truthy);
         if let Some(ref mut falsy) = falsy {
-            *falsy = Box::new(process_ast(take(falsy))?);
+            **falsy = process_ast(take(falsy))?;
+            push_ancestry(&mut node.seeking_parent, falsy);
         }
     } else {
         unreachable!()
@@ -93,10 +103,10 @@ fn process_transform(node: Ast) -> Result<Ast> {
         ref mut delete,
     } = node.kind
     {
-        *pattern = Box::new(process_ast(take(pattern))?);
-        *update = Box::new(process_ast(take(update))?);
+        **pattern = process_ast(take(pattern))?;
+        **update = process_ast(take(update))?;
         if let Some(ref mut delete) = delete {
-            *delete = Box::new(process_ast(take(delete))?);
+            **delete = process_ast(take(delete))?;
         }
     }
 
@@ -115,10 +125,16 @@ fn process_unary(node: Ast) -> Result<Ast> {
                     *v = -*v;
                     Ok(result)
                 }
-                _ => Ok(Ast::new(
-                    AstKind::Unary(UnaryOp::Minus(Box::new(result))),
-                    node.char_index,
-                )),
+                _ => {
+                    let mut seeking_parent = Vec::new();
+                    push_ancestry(&mut seeking_parent, &mut result);
+                    let mut node = Ast::new(
+                        AstKind::Unary(UnaryOp::Minus(Box::new(result))),
+                        node.char_index,
+                    );
+                    node.seeking_parent = seeking_parent;
+                    Ok(node)
+                }
             }
         }
 
@@ -126,6 +142,7 @@ fn process_unary(node: Ast) -> Result<Ast> {
         AstKind::Unary(UnaryOp::ArrayConstructor(ref mut exprs)) => {
             for expr in exprs {
                 *expr = process_ast(take(expr))?;
+                push_ancestry(&mut node.seeking_parent, expr);
             }
             Ok(node)
         }
@@ -133,9 +150,11 @@ fn process_unary(node: Ast) -> Result<Ast> {
         // Process all the keys and values in an object constructor
         AstKind::Unary(UnaryOp::ObjectConstructor(ref mut object)) => {
             for pair in object {
-                let key = take(&mut pair.0);
-                let value = take(&mut pair.1);
-                *pair = (process_ast(key)?, process_ast(value)?);
+                let mut key = process_ast(take(&mut pair.0))?;
+                push_ancestry(&mut node.seeking_parent, &mut key);
+                let mut value = process_ast(take(&mut pair.1))?;
+                push_ancestry(&mut node.seeking_parent, &mut value);
+                *pair = (key, value);
             }
             Ok(node)
         }
@@ -160,9 +179,22 @@ fn process_binary(node: Ast) -> Result<Ast> {
         AstKind::Binary(BinaryOp::IndexBind, ref mut lhs, ref mut rhs) => {
             process_index_bind(node.char_index, lhs, rhs)
         }
+        AstKind::Binary(BinaryOp::Apply, ref mut lhs, ref mut rhs) => {
+            **lhs = process_ast(take(lhs))?;
+            **rhs = process_ast(take(rhs))?;
+            Ok(node)
+        }
+        AstKind::Binary(BinaryOp::Bind, ref mut lhs, ref mut rhs) => {
+            **lhs = process_ast(take(lhs))?;
+            **rhs = process_ast(take(rhs))?;
+            push_ancestry(&mut node.seeking_parent, rhs);
+            Ok(node)
+        }
         AstKind::Binary(_, ref mut lhs, ref mut rhs) => {
-            *lhs = Box::new(process_ast(take(lhs))?);
-            *rhs = Box::new(process_ast(take(rhs))?);
+            **lhs = process_ast(take(lhs))?;
+            push_ancestry(&mut node.seeking_parent, lhs);
+            **rhs = process_ast(take(rhs))?;
+            push_ancestry(&mut node.seeking_parent, rhs);
             Ok(node)
         }
         _ => unreachable!(),
@@ -177,10 +209,16 @@ fn process_path(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>) -> Re
     let mut result = if matches!(left_step.kind, AstKind::Path(_)) {
         left_step
     } else {
-        Ast::new(AstKind::Path(vec![left_step]), char_index)
+        // If the left_step is a parent, the path is seeking its slot
+        let seeking_parent = match left_step.kind {
+            AstKind::Parent(ref slot) => vec![slot.clone()],
+            _ => Vec::new(),
+        };
+        let mut result = Ast::new(AstKind::Path(vec![left_step]), char_index);
+        result.seeking_parent = seeking_parent;
+        result
     };
 
-    // TODO: If the lhs is a Parent (parser.js:997)
     // TODO: If the rhs is a Function (parser.js:1001)
 
     if let AstKind::Path(ref mut steps) = result.kind {
@@ -209,10 +247,10 @@ fn process_path(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>) -> Re
                 }
 
                 // If the first or last step is an array constructor, it shouldn't be flattened
-                AstKind::Unary(UnaryOp::ArrayConstructor(..)) => {
-                    if step_index == 0 || step_index == last_index {
-                        step.cons_array = true;
-                    }
+                AstKind::Unary(UnaryOp::ArrayConstructor(..))
+                    if step_index == 0 || step_index == last_index =>
+                {
+                    step.cons_array = true;
                 }
 
                 _ => (),
@@ -225,6 +263,8 @@ fn process_path(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>) -> Re
         result.keep_singleton_array = keep_singleton_array;
     }
 
+    resolve_ancestry(&mut result)?;
+
     Ok(result)
 }
 
@@ -245,12 +285,22 @@ fn process_predicate(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>)
         return Err(Error::S0209InvalidPredicate(char_index));
     }
 
-    let filter = Ast::new(
-        AstKind::Filter(Box::new(process_ast(take(rhs))?)),
-        char_index,
-    );
+    let mut predicate = process_ast(take(rhs))?;
 
-    // TODO: seekingParent (parser.js:1074)
+    // Any parents in the predicate that refer to the step's own parent can be resolved here,
+    // the rest are passed up to the step
+    if !predicate.seeking_parent.is_empty() {
+        for slot in predicate.seeking_parent.iter_mut() {
+            if slot.level == 1 {
+                seek_parent(node, slot)?;
+            } else {
+                slot.level -= 1;
+            }
+        }
+        push_ancestry(&mut node.seeking_parent, &mut predicate);
+    }
+
+    let filter = Ast::new(AstKind::Filter(Box::new(predicate)), char_index);
 
     // Add the filter to the node. If it's a step in a path, it goes in stages, otherwise in predicates
     if in_path {
@@ -383,29 +433,118 @@ fn process_order_by(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut SortTerms)
     };
 
     // Process all the sort terms
+    let mut seeking_parent = Vec::new();
     for pair in rhs.iter_mut() {
         *pair = (process_ast(take(&mut pair.0))?, pair.1);
+        push_ancestry(&mut seeking_parent, &mut pair.0);
     }
 
     if let AstKind::Path(ref mut steps) = result.kind {
-        steps.push(Ast::new(AstKind::Sort(take(rhs)), char_index));
+        let mut sort_step = Ast::new(AstKind::Sort(take(rhs)), char_index);
+        sort_step.seeking_parent = seeking_parent;
+        steps.push(sort_step);
     }
 
+    resolve_ancestry(&mut result)?;
+
     Ok(result)
 }
 
-fn process_function(proc: &mut Box<Ast>, args: &mut [Ast]) -> Result<()> {
-    *proc = Box::new(process_ast(take(&mut *proc))?);
+fn process_function(
+    proc: &mut Box<Ast>,
+    args: &mut [Ast],
+    seeking_parent: &mut Vec<Slot>,
+) -> Result<()> {
+    **proc = process_ast(take(&mut **proc))?;
     for arg in args.iter_mut() {
         *arg = process_ast(take(arg))?;
+        push_ancestry(seeking_parent, arg);
     }
     Ok(())
 }
 
 fn process_lambda(body: &mut Box<Ast>) -> Result<()> {
     let new_body = process_ast(take(body))?;
-    let new_body = tail_call_optimize(new_body)?;
-    *body = Box::new(new_body);
+    **body = tail_call_optimize(new_body)?;
+    Ok(())
+}
+
+// Move any unresolved parent slots from a processed child node into its parent's list. A parent
+// operator itself contributes its own slot.
+fn push_ancestry(seeking_parent: &mut Vec<Slot>, value: &mut Ast) {
+    seeking_parent.append(&mut value.seeking_parent);
+    if let AstKind::Parent(ref slot) = value.kind {
+        seeking_parent.push(slot.clone());
+    }
+}
+
+// Walk back through a node to find the step that the slot refers to. Each name or wildcard step
+// that is passed decrements the level of the slot, and the step that brings it to zero is the
+// ancestor. Each parent operator that is passed increments the level.
+fn seek_parent(node: &mut Ast, slot: &mut Slot) -> Result<()> {
+    match node.kind {
+        AstKind::Name(..) | AstKind::Wildcard => {
+            slot.level -= 1;
+            if slot.level == 0 {
+                node.ancestors.push(slot.label.clone());
+                node.tuple = true;
+            }
+        }
+        AstKind::Parent(..) => slot.level += 1,
+        AstKind::Block(ref mut exprs) => {
+            // Look in the last expression in the block
+            if let Some(last) = exprs.last_mut() {
+                node.tuple = true;
+                seek_parent(last, slot)?;
+            }
+        }
+        AstKind::Path(ref mut steps) => {
+            // Start from the last step and walk back through the path
+            node.tuple = true;
+            for step in steps.iter_mut().rev() {
+                seek_parent(step, slot)?;
+                if slot.level == 0 {
+                    break;
+                }
+            }
+        }
+        _ => return Err(Error::S0217ParentNotDerivable(node.char_index)),
+    }
+    Ok(())
+}
+
+// Resolve the slots that the last step of a path is seeking against the previous steps. Any
+// slots that can't be resolved within the path are passed up to the path itself.
+fn resolve_ancestry(path: &mut Ast) -> Result<()> {
+    if let AstKind::Path(ref mut steps) = path.kind {
+        let last_index = steps.len() - 1;
+        let last_step = &mut steps[last_index];
+
+        let mut slots = take(&mut last_step.seeking_parent);
+        if let AstKind::Parent(ref slot) = last_step.kind {
+            slots.push(slot.clone());
+        }
+
+        for mut slot in slots {
+            let mut index = last_index;
+            while slot.level > 0 {
+                if index == 0 {
+                    path.seeking_parent.push(slot);
+                    break;
+                }
+
+                // Try the previous step, skipping over contiguous steps that bind the focus
+                index -= 1;
+                while index > 0 && steps[index].focus.is_some() && steps[index - 1].focus.is_some()
+                {
+                    index -= 1;
+                }
+
+                seek_parent(&mut steps[index], &mut slot)?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -419,6 +558,7 @@ fn tail_call_optimize(mut expr: Ast) -> Result<Ast> {
                     args: vec![],
                     thunk: true,
                     body: Box::new(expr),
+                    signature: None,
                 },
                 char_index,
             );
@@ -426,9 +566,9 @@ fn tail_call_optimize(mut expr: Ast) -> Result<Ast> {
             Ok(thunk)
         }
         AstKind::Ternary { truthy, falsy, .. } => {
-            *truthy = Box::new(tail_call_optimize(take(truthy))?);
+            **truthy = tail_call_optimize(take(truthy))?;
             if let Some(inner) = falsy {
-                *falsy = Some(Box::new(tail_call_optimize(take(inner))?));
+                **inner = tail_call_optimize(take(inner))?;
             }
             Ok(expr)
         }
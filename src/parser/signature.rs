@@ -0,0 +1,332 @@
+use bumpalo::Bump;
+
+use crate::evaluator::value::{ArrayFlags, Value};
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantifier {
+    One,
+    Optional,
+    OneOrMore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Param {
+    /// The type of the parameter as it appears in the signature, e.g. `s`, `a` or `(ns)`
+    kind: String,
+
+    /// The value type symbols that this parameter accepts, where `m` represents a missing
+    /// (undefined) argument
+    accepts: String,
+
+    quantifier: Quantifier,
+
+    /// Whether the context can be substituted for this parameter when it's missing
+    context: bool,
+
+    /// The type parameter of an array or function, e.g. the `s` of `a<s>`
+    subtype: Option<String>,
+}
+
+impl Param {
+    fn new(kind: impl Into<String>, accepts: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            accepts: accepts.into(),
+            quantifier: Quantifier::One,
+            context: false,
+            subtype: None,
+        }
+    }
+
+    fn is_array(&self) -> bool {
+        self.kind == "a"
+    }
+
+    fn bounds(&self) -> (usize, usize) {
+        match self.quantifier {
+            Quantifier::One => (1, 1),
+            Quantifier::Optional => (0, 1),
+            Quantifier::OneOrMore => (1, usize::MAX),
+        }
+    }
+}
+
+/// A function signature, as described in <https://docs.jsonata.org/programming#function-signatures>.
+///
+/// The signature is compiled once when parsed, and is used to validate (and coerce) the arguments
+/// of every invocation of the function it's attached to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    definition: String,
+    params: Vec<Param>,
+}
+
+impl Signature {
+    /// Parse a signature definition such as `<n-n:n>`. The char_index is the position of the
+    /// definition in the source, and is used for reporting errors.
+    pub fn parse(definition: &str, char_index: usize) -> Result<Signature> {
+        let symbols: Vec<char> = definition.chars().collect();
+        let mut params: Vec<Param> = Vec::new();
+        let mut position = 1;
+
+        while position < symbols.len() {
+            let symbol = symbols[position];
+            match symbol {
+                // The return type is ignored
+                ':' => break,
+                's' | 'n' | 'b' | 'l' | 'o' => {
+                    params.push(Param::new(symbol, format!("{}m", symbol)));
+                }
+                'a' => params.push(Param::new("a", "asnblfom")),
+                'f' => params.push(Param::new("f", "f")),
+                'j' => params.push(Param::new("j", "asnblom")),
+                'x' => params.push(Param::new("x", "asnblfom")),
+                '-' => {
+                    if let Some(param) = params.last_mut() {
+                        param.context = true;
+                        param.quantifier = Quantifier::Optional;
+                    }
+                }
+                '?' => {
+                    if let Some(param) = params.last_mut() {
+                        param.quantifier = Quantifier::Optional;
+                    }
+                }
+                '+' => {
+                    if let Some(param) = params.last_mut() {
+                        param.quantifier = Quantifier::OneOrMore;
+                    }
+                }
+                '(' => {
+                    let end = find_closing_bracket(&symbols, position, '(', ')');
+                    let choice: String = symbols[position + 1..end].iter().collect();
+                    if choice.contains('<') {
+                        return Err(Error::S0402ParameterizedChoiceGroup(
+                            char_index + position,
+                            choice,
+                        ));
+                    }
+                    params.push(Param::new(format!("({})", choice), format!("{}m", choice)));
+                    position = end;
+                }
+                '<' => match params.last_mut() {
+                    Some(param) if param.kind == "a" || param.kind == "f" => {
+                        let end = find_closing_bracket(&symbols, position, '<', '>');
+                        param.subtype = Some(symbols[position + 1..end].iter().collect());
+                        position = end;
+                    }
+                    param => {
+                        return Err(Error::S0401InvalidTypeParameter(
+                            char_index + position,
+                            param.map(|p| p.kind.clone()).unwrap_or_default(),
+                        ));
+                    }
+                },
+                _ => (),
+            }
+            position += 1;
+        }
+
+        Ok(Signature {
+            definition: definition.to_string(),
+            params,
+        })
+    }
+
+    /// The signature as it was defined.
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    /// The number of parameters declared by the signature.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// Validate the arguments of an invocation against the signature, returning the arguments
+    /// that should be passed to the function. Missing arguments marked with `-` are replaced by
+    /// the context, and non-array values passed for array parameters are wrapped in an array.
+    /// Optional arguments that weren't given at the end are left out, so that the function can
+    /// tell how many it was called with.
+    pub fn validate<'a>(
+        &self,
+        arena: &'a Bump,
+        char_index: usize,
+        name: &str,
+        args: &[&'a Value<'a>],
+        context: &'a Value<'a>,
+    ) -> Result<Vec<&'a Value<'a>>> {
+        let symbols: Vec<char> = args.iter().map(|arg| type_symbol(arg)).collect();
+
+        let mut counts = Vec::with_capacity(self.params.len());
+        if !match_params(&self.params, &symbols, true, &mut counts) {
+            return Err(self.validation_error(char_index, name, &symbols));
+        }
+
+        let arg = |index: usize| args.get(index).copied().unwrap_or_else(Value::undefined);
+
+        let mut validated = Vec::with_capacity(self.params.len());
+        let mut arg_index = 0;
+        let mut symbol_index = 0;
+
+        for (param, count) in self.params.iter().zip(counts) {
+            if count == 0 {
+                if param.context {
+                    let context = if context.is_array() && context.has_flags(ArrayFlags::WRAPPED) {
+                        context.get_member(0)
+                    } else {
+                        context
+                    };
+                    if !param.accepts.contains(type_symbol(context)) {
+                        return Err(Error::T0411ContextValueNotCompatible(
+                            char_index,
+                            arg_index + 1,
+                            name.to_string(),
+                        ));
+                    }
+                    validated.push(context);
+                } else if arg_index < args.len() {
+                    validated.push(arg(arg_index));
+                    arg_index += 1;
+                }
+                continue;
+            }
+
+            let matched = &symbols[symbol_index..symbol_index + count];
+            symbol_index += count;
+
+            for single in matched {
+                let mut value = arg(arg_index);
+
+                if param.is_array() && *single == 'm' {
+                    value = Value::undefined();
+                } else if param.is_array() {
+                    if let Some(ref subtype) = param.subtype {
+                        let array_ok = if *single != 'a' {
+                            matched.iter().collect::<String>() == *subtype
+                        } else if value.is_empty() {
+                            true
+                        } else {
+                            let item_type = type_symbol(value.get_member(0));
+                            subtype.starts_with(item_type)
+                                && value.members().all(|item| type_symbol(item) == item_type)
+                        };
+
+                        if !array_ok {
+                            return Err(Error::T0412ArgumentMustBeArrayOfType(
+                                char_index,
+                                arg_index + 1,
+                                name.to_string(),
+                                array_type_name(subtype).to_string(),
+                            ));
+                        }
+                    }
+
+                    // The function expects an array, so make it one if it isn't
+                    if *single != 'a' {
+                        value = Value::wrap_in_array(arena, value, ArrayFlags::empty());
+                    }
+                }
+
+                validated.push(value);
+                arg_index += 1;
+            }
+        }
+
+        Ok(validated)
+    }
+
+    // Find the first argument that fails to match by matching each successively longer prefix of
+    // the parameters against the arguments.
+    fn validation_error(&self, char_index: usize, name: &str, symbols: &[char]) -> Error {
+        let mut good_to = 0;
+        for index in 0..self.params.len() {
+            let mut counts = Vec::with_capacity(index + 1);
+            if !match_params(&self.params[..=index], symbols, false, &mut counts) {
+                break;
+            }
+            good_to = counts.iter().sum();
+        }
+        Error::T0410ArgumentNotValid(char_index, good_to + 1, name.to_string())
+    }
+}
+
+// Match the argument type symbols against the parameters, recording how many arguments each
+// parameter consumed. Parameters are greedy, and backtrack when the rest fail to match. If
+// anchored, all of the arguments must be consumed.
+fn match_params(
+    params: &[Param],
+    symbols: &[char],
+    anchored: bool,
+    counts: &mut Vec<usize>,
+) -> bool {
+    let Some((param, rest)) = params.split_first() else {
+        return !anchored || symbols.is_empty();
+    };
+
+    let (min, max) = param.bounds();
+    let available = symbols
+        .iter()
+        .take_while(|symbol| param.accepts.contains(**symbol))
+        .count();
+
+    for count in (min..=max.min(available)).rev() {
+        counts.push(count);
+        if match_params(rest, &symbols[count..], anchored, counts) {
+            return true;
+        }
+        counts.pop();
+    }
+
+    false
+}
+
+fn find_closing_bracket(symbols: &[char], start: usize, open: char, close: char) -> usize {
+    let mut depth = 1;
+    let mut position = start + 1;
+    while position < symbols.len() {
+        if symbols[position] == close {
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        } else if symbols[position] == open {
+            depth += 1;
+        }
+        position += 1;
+    }
+    position
+}
+
+fn type_symbol(value: &Value) -> char {
+    match value {
+        Value::Undefined => 'm',
+        Value::Null => 'l',
+        Value::Number(..) | Value::Decimal(..) | Value::RawNumber(..) => 'n',
+        Value::Bool(..) => 'b',
+        Value::String(..) => 's',
+        Value::Array(..) | Value::Range(..) => 'a',
+        Value::Object(..) => 'o',
+        Value::Regex(..)
+        | Value::Lambda { .. }
+        | Value::NativeFn { .. }
+        | Value::Transformer { .. } => 'f',
+    }
+}
+
+fn array_type_name(subtype: &str) -> &str {
+    match subtype {
+        "a" => "arrays",
+        "b" => "booleans",
+        "f" => "functions",
+        "n" => "numbers",
+        "o" => "objects",
+        "s" => "strings",
+        _ => subtype,
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{Error, Result};
 
 use super::ast::*;
+use super::signature::Signature;
 use super::tokenizer::{Token, TokenKind};
 use super::Parser;
 
@@ -51,7 +52,10 @@ impl Symbol for Token {
             )),
             TokenKind::Asterisk => Ok(Ast::new(AstKind::Wildcard, self.char_index)),
             TokenKind::Descendent => Ok(Ast::new(AstKind::Descendent, self.char_index)),
-            TokenKind::PercentSign => Ok(Ast::new(AstKind::Parent, self.char_index)),
+            TokenKind::PercentSign => Ok(Ast::new(
+                AstKind::Parent(Slot::new(self.char_index)),
+                self.char_index,
+            )),
 
             // Block of expressions
             TokenKind::LeftParen => {
@@ -139,6 +143,8 @@ impl Symbol for Token {
                 ))
             }
 
+            TokenKind::End => Err(Error::S0207UnexpectedEndOfExpression(self.char_index)),
+
             _ => Err(Error::S0211InvalidUnary(
                 self.char_index,
                 self.kind.to_string(),
@@ -224,12 +230,19 @@ impl Symbol for Token {
                         name.clone()
                     }
                     AstKind::Var(ref name) => name.clone(),
-                    _ => unreachable!(),
+                    // Any other expression can be invoked, but it has no name
+                    _ => String::new(),
                 };
 
                 let func: Ast;
 
                 if is_lambda {
+                    let signature = if parser.token().kind == TokenKind::LeftAngleBracket {
+                        Some(parse_signature(parser)?)
+                    } else {
+                        None
+                    };
+
                     parser.expect(TokenKind::LeftBrace)?;
                     let body = Box::new(parser.expression(0)?);
                     func = Ast::new(
@@ -238,6 +251,7 @@ impl Symbol for Token {
                             args,
                             body,
                             thunk: false,
+                            signature,
                         },
                         self.char_index,
                     );
@@ -417,3 +431,24 @@ fn parse_object(parser: &mut Parser) -> Result<Object> {
     parser.expect(TokenKind::RightBrace)?;
     Ok(object)
 }
+
+/// Parses a function signature following the arguments of a lambda definition.
+fn parse_signature(parser: &mut Parser) -> Result<Signature> {
+    let char_index = parser.token().char_index;
+    let mut definition = parser.tokenizer.string_from_token(parser.token());
+    let mut depth = 1;
+
+    // Collect the tokens up to the matching closing angle bracket
+    while depth > 0 && !matches!(parser.token().kind, TokenKind::LeftBrace | TokenKind::End) {
+        parser.next_token()?;
+        match parser.token().kind {
+            TokenKind::LeftAngleBracket => depth += 1,
+            TokenKind::RightAngleBracket => depth -= 1,
+            _ => (),
+        }
+        definition.push_str(&parser.tokenizer.string_from_token(parser.token()));
+    }
+    parser.expect(TokenKind::RightAngleBracket)?;
+
+    Signature::parse(&definition, char_index)
+}
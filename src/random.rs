@@ -0,0 +1,29 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// The source of randomness for `$random`, `$shuffle` and `$uuid`.
+pub trait RandomSource: Send + Sync {
+    /// Create the random number generator for a single evaluation.
+    fn generator(&self) -> Box<dyn RngCore>;
+}
+
+/// Randomness from the thread-local generator provided by `rand`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRandom;
+
+impl RandomSource for SystemRandom {
+    fn generator(&self) -> Box<dyn RngCore> {
+        Box::new(rand::rng())
+    }
+}
+
+/// A generator seeded with the same value for every evaluation, so that each evaluation produces
+/// the same sequence of random values.
+#[derive(Debug, Clone, Copy)]
+pub struct SeededRandom(pub u64);
+
+impl RandomSource for SeededRandom {
+    fn generator(&self) -> Box<dyn RngCore> {
+        Box::new(StdRng::seed_from_u64(self.0))
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use bumpalo::Bump;
+
+use crate::evaluator::functions::FunctionContext;
+use crate::evaluator::value::Value;
+use crate::parser::ast::Signature;
+use crate::Result;
+
+/// A custom function that can be shared between threads and used by any number of evaluations.
+pub type SharedFunction = dyn for<'a, 'e> Fn(FunctionContext<'a, 'e>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>
+    + Send
+    + Sync;
+
+struct RegisteredFunction {
+    arity: usize,
+    signature: Option<Signature>,
+    implementation: Arc<SharedFunction>,
+}
+
+/// Which of the built-in functions can be called.
+#[derive(Debug, Clone)]
+enum Builtins {
+    /// Only the listed functions
+    Allow(HashSet<String>),
+
+    /// All but the listed functions
+    Deny(HashSet<String>),
+}
+
+/// A library of named functions which can be attached to any number of expressions, either with
+/// [`CompiledExpression::with_registry`](crate::CompiledExpression::with_registry) or
+/// [`JsonAta::set_registry`](crate::JsonAta::set_registry).
+///
+/// Functions in the registry take precedence over the standard library, which can also be
+/// restricted, e.g. to sandbox untrusted expressions. Variables bound in the expression itself
+/// take precedence over both.
+pub struct FunctionRegistry {
+    functions: HashMap<String, RegisteredFunction>,
+    builtins: Builtins,
+}
+
+impl FunctionRegistry {
+    /// Create a registry that includes the standard library.
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+            builtins: Builtins::Deny(HashSet::new()),
+        }
+    }
+
+    /// Create a registry without the standard library, so that only the functions registered
+    /// with it can be called.
+    pub fn without_stdlib() -> Self {
+        Self {
+            functions: HashMap::new(),
+            builtins: Builtins::Allow(HashSet::new()),
+        }
+    }
+
+    /// Only allow the given built-in functions to be called, e.g. `["sum", "string"]`.
+    pub fn allow_builtins<'n>(&mut self, names: impl IntoIterator<Item = &'n str>) {
+        self.builtins = Builtins::Allow(names.into_iter().map(String::from).collect());
+    }
+
+    /// Prevent the given built-in functions from being called, e.g. `["now", "random"]`.
+    pub fn deny_builtins<'n>(&mut self, names: impl IntoIterator<Item = &'n str>) {
+        match self.builtins {
+            Builtins::Allow(ref mut allowed) => {
+                for name in names {
+                    allowed.remove(name);
+                }
+            }
+            Builtins::Deny(ref mut denied) => denied.extend(names.into_iter().map(String::from)),
+        }
+    }
+
+    /// Whether the built-in function with the given name can be called.
+    pub fn allows_builtin(&self, name: &str) -> bool {
+        match self.builtins {
+            Builtins::Allow(ref allowed) => allowed.contains(name),
+            Builtins::Deny(ref denied) => !denied.contains(name),
+        }
+    }
+
+    pub fn register<F>(&mut self, name: &str, arity: usize, implementation: F)
+    where
+        F: for<'a, 'e> Fn(FunctionContext<'a, 'e>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.register_shared(name, arity, Arc::new(implementation));
+    }
+
+    /// Register a function whose arguments are validated against a JSONata function signature,
+    /// e.g. `<n-n?:n>`, before it is called. The arity of the function is taken from the
+    /// signature.
+    pub fn register_with_signature<F>(
+        &mut self,
+        name: &str,
+        signature: &str,
+        implementation: F,
+    ) -> Result<()>
+    where
+        F: for<'a, 'e> Fn(FunctionContext<'a, 'e>, &[&'a Value<'a>]) -> Result<&'a Value<'a>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let signature = Signature::parse(signature, 0)?;
+        self.functions.insert(
+            name.to_string(),
+            RegisteredFunction {
+                arity: signature.len(),
+                signature: Some(signature),
+                implementation: Arc::new(implementation),
+            },
+        );
+        Ok(())
+    }
+
+    /// Register a function that is already shared, e.g. with another registry.
+    pub fn register_shared(
+        &mut self,
+        name: &str,
+        arity: usize,
+        implementation: Arc<SharedFunction>,
+    ) {
+        self.functions.insert(
+            name.to_string(),
+            RegisteredFunction {
+                arity,
+                signature: None,
+                implementation,
+            },
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Create a function value for a registered function, if there is one with the given name.
+    /// The name is returned too, borrowed from the registry.
+    pub(crate) fn get<'a>(
+        &'a self,
+        arena: &'a Bump,
+        name: &str,
+    ) -> Option<(&'a str, &'a Value<'a>)> {
+        let (name, function) = self.functions.get_key_value(name)?;
+        let value = Value::closure(
+            arena,
+            name,
+            function.arity,
+            function.signature.as_ref(),
+            &*function.implementation,
+        );
+        Some((name, value))
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .field("builtins", &self.builtins)
+            .finish()
+    }
+}